@@ -7,6 +7,12 @@ pub enum CommonError {
     InvalidDirectoryPath { path: String },
     #[error("Invalid name pattern")]
     InvalidNamePattern,
+    #[error("Invalid regular expression\n{}", error)]
+    InvalidRegex { error: String },
+    #[error("Unknown modifier \"{}\" at position {}", modifier, position)]
+    UnknownModifier { modifier: String, position: usize },
+    #[error("Named marker \"#{{{}}}\" does not match any named group", name)]
+    UnknownMarkerName { name: String },
     #[error("Files for pattern \"{}\" not found", pattern)]
     NoMatchingFiles { pattern: String },
     #[error("Not able to replace existing file: {}", filename)]
@@ -19,4 +25,136 @@ pub enum CommonError {
     InvalidMarkersValue,
     #[error("Marker's value is greater than fragments count")]
     TooBigMarkerValue,
+    #[error("Name \"{}\" is not valid UTF-8 and cannot be used as text", name)]
+    NonUnicodeName { name: String },
+    #[error(
+        "Several files would be moved to the same path:\n{}",
+        list_collisions(groups)
+    )]
+    TargetCollision { groups: Vec<(String, Vec<String>)> },
+    #[error(
+        "Failed to move {} -> {}: {}\n{}",
+        origin,
+        target,
+        error,
+        describe_rollback(*completed, rollback_failures)
+    )]
+    MoveFailed {
+        origin: String,
+        target: String,
+        error: Box<CommonError>,
+        completed: usize,
+        rollback_failures: Vec<String>,
+    },
+    #[error("Journal {} is invalid\n{}", path, error)]
+    InvalidJournal { path: String, error: String },
+    #[error("Journal {} not found", id)]
+    JournalNotFound { id: String },
+    #[error("No journal to undo")]
+    NothingToUndo,
+    #[error("No interrupted run to resume or roll back")]
+    NoInterruptedRun,
+    #[error(
+        "Run {} was interrupted, finish it with --resume or revert it with --rollback",
+        id
+    )]
+    UnfinishedJournal { id: String },
+    #[error("Run {} was not interrupted", id)]
+    NotInterrupted { id: String },
+    #[error("Journal {} is already undone", id)]
+    AlreadyUndone { id: String },
+    #[error("File {} was changed or removed after the run", path)]
+    FileChanged { path: String },
+    #[error(
+        "Option {} contradicts the options run {} was started with",
+        option,
+        id
+    )]
+    ResumeOptionsMismatch { id: String, option: String },
+    #[error("Not able to replace {} that is a source of this run", filename)]
+    OverwritesSource { filename: String },
+    #[error("Aborted, no file was moved")]
+    Aborted,
+    #[error("Edited list is invalid, {}", reason)]
+    InvalidEdit { reason: String },
+    #[error("Editor {} failed with {}", editor, status)]
+    EditorFailed { editor: String, status: String },
+    #[error("{} problems found:\n{}", errors.len(), list_errors(errors))]
+    Multiple { errors: Vec<CommonError> },
+}
+
+impl CommonError {
+    /// Exit code of the command line tool for the error
+    ///
+    /// Codes are grouped: 10-19 for templates and patterns, 20-29 for problems
+    /// of moving files, 30-39 for journals, undo and recovery, 40-49 for
+    /// interaction with the user and 50 for several problems at once.
+    /// Code 2 is used by argument parsing and 3 by runs that skipped files
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CommonError::InvalidDirectoryPath { .. } => 10,
+            CommonError::InvalidNamePattern => 11,
+            CommonError::InvalidRegex { .. } => 12,
+            CommonError::UnknownModifier { .. } => 13,
+            CommonError::UnknownMarkerName { .. } => 14,
+            CommonError::NoMatchingFiles { .. } => 15,
+            CommonError::EmptyInput => 16,
+            CommonError::InvalidMarkersValue => 17,
+            CommonError::TooBigMarkerValue => 18,
+            CommonError::NonUnicodeName { .. } => 19,
+            CommonError::FilenameAlreadyExists { .. } => 20,
+            CommonError::TargetCollision { .. } => 21,
+            CommonError::OverwritesSource { .. } => 22,
+            CommonError::MoveFailed { .. } => 23,
+            CommonError::IOError { .. } => 24,
+            CommonError::InvalidJournal { .. } => 30,
+            CommonError::JournalNotFound { .. } => 31,
+            CommonError::NothingToUndo => 32,
+            CommonError::NoInterruptedRun => 33,
+            CommonError::UnfinishedJournal { .. } => 34,
+            CommonError::NotInterrupted { .. } => 35,
+            CommonError::AlreadyUndone { .. } => 36,
+            CommonError::FileChanged { .. } => 37,
+            CommonError::ResumeOptionsMismatch { .. } => 38,
+            CommonError::Aborted => 40,
+            CommonError::InvalidEdit { .. } => 41,
+            CommonError::EditorFailed { .. } => 42,
+            CommonError::Multiple { .. } => 50,
+        }
+    }
+}
+
+/// Formats every error on its own line
+fn list_errors(errors: &[CommonError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("- {}", error))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats every target with the files colliding on it
+fn list_collisions(groups: &[(String, Vec<String>)]) -> String {
+    groups
+        .iter()
+        .map(|(target, origins)| format!("- {} <- {}", target, origins.join(", ")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Describes the result of reversing completed moves
+fn describe_rollback(completed: usize, failures: &[String]) -> String {
+    if failures.is_empty() {
+        return format!("All {} completed moves were rolled back", completed);
+    }
+    let failures: Vec<_> = failures
+        .iter()
+        .map(|failure| format!("- {}", failure))
+        .collect();
+    format!(
+        "Rollback is incomplete, {} of {} completed moves were not reversed:\n{}",
+        failures.len(),
+        completed,
+        failures.join("\n")
+    )
 }
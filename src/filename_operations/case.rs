@@ -0,0 +1,171 @@
+//! Case conversions applied to fragments by marker modifiers like `#1:upper`
+//!
+//! All conversions use Unicode case mapping of the standard library
+
+/// Transformation of a fragment requested by a marker modifier
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Modifier {
+    /// `upper`, `Hello World` -> `HELLO WORLD`
+    Upper,
+    /// `lower`, `Hello World` -> `hello world`
+    Lower,
+    /// `title`, `hello wORLD` -> `Hello World`
+    Title,
+    /// `snake`, `Hello World` -> `hello_world`
+    Snake,
+    /// `kebab`, `Hello World` -> `hello-world`
+    Kebab,
+    /// `camel`, `Hello World` -> `helloWorld`
+    Camel,
+}
+
+impl Modifier {
+    /// Returns [`Modifier`] by its name or [`None`] if name is unknown
+    ///
+    /// # Examples
+    /// ```
+    /// use mmv::filename_operations::case::Modifier;
+    /// assert_eq!(Modifier::parse("upper"), Some(Modifier::Upper));
+    /// assert_eq!(Modifier::parse("shout"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "upper" => Some(Self::Upper),
+            "lower" => Some(Self::Lower),
+            "title" => Some(Self::Title),
+            "snake" => Some(Self::Snake),
+            "kebab" => Some(Self::Kebab),
+            "camel" => Some(Self::Camel),
+            _ => None,
+        }
+    }
+
+    /// Applies modifier to the text
+    ///
+    /// # Examples
+    /// ```
+    /// use mmv::filename_operations::case::Modifier;
+    /// assert_eq!(Modifier::Snake.apply("myHTMLFile v2"), "my_html_file_v2");
+    /// assert_eq!(Modifier::Title.apply("straße ǆungla"), "Straße ǅungla");
+    /// ```
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Self::Upper => text.to_uppercase(),
+            Self::Lower => text.to_lowercase(),
+            Self::Title => title_case(text),
+            Self::Snake => lower_words(text).join("_"),
+            Self::Kebab => lower_words(text).join("-"),
+            Self::Camel => {
+                let words = lower_words(text);
+                let mut camel = String::new();
+                for (index, word) in words.iter().enumerate() {
+                    if index == 0 {
+                        camel.push_str(word);
+                    } else {
+                        camel.push_str(&capitalize(word));
+                    }
+                }
+                camel
+            }
+        }
+    }
+}
+
+/// Returns titlecase form of the character
+///
+/// Differs from uppercase for digraphs like `ǆ` and for characters whose
+/// uppercase form has several characters like `ß`
+fn title_char(symbol: char) -> String {
+    match symbol {
+        'Ǆ' | 'ǅ' | 'ǆ' => String::from('ǅ'),
+        'Ǉ' | 'ǈ' | 'ǉ' => String::from('ǈ'),
+        'Ǌ' | 'ǋ' | 'ǌ' => String::from('ǋ'),
+        'Ǳ' | 'ǲ' | 'ǳ' => String::from('ǲ'),
+        _ => {
+            let mut upper = symbol.to_uppercase();
+            let mut title: String = upper.next().into_iter().collect();
+            title.extend(upper.flat_map(char::to_lowercase));
+            title
+        }
+    }
+}
+
+/// Uppercases the first character of the word and lowercases the rest
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(first) => title_char(first) + &chars.as_str().to_lowercase(),
+    }
+}
+
+/// Capitalizes every word keeping separators between words
+fn title_case(text: &str) -> String {
+    let mut title = String::new();
+    let mut word = String::new();
+    for symbol in text.chars() {
+        if symbol.is_alphanumeric() {
+            word.push(symbol);
+        } else {
+            title.push_str(&capitalize(&word));
+            title.push(symbol);
+            word.clear();
+        }
+    }
+    title + &capitalize(&word)
+}
+
+/// Splits text into lowercase words
+///
+/// Words are separated by non-alphanumeric characters and by case changes,
+/// so `myHTMLFile` is split into `my`, `html` and `file`
+fn lower_words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+    for (index, &symbol) in chars.iter().enumerate() {
+        if !symbol.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if !word.is_empty() && symbol.is_uppercase() {
+            let previous = chars[index - 1];
+            let next_is_lower = chars.get(index + 1).is_some_and(|next| next.is_lowercase());
+            if !previous.is_uppercase() || next_is_lower {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(symbol);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words.into_iter().map(|word| word.to_lowercase()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Modifier;
+
+    #[test]
+    fn modifiers() {
+        let check = |modifier: &str, text: &str, expected: &str| {
+            assert_eq!(Modifier::parse(modifier).unwrap().apply(text), expected);
+        };
+        check("upper", "straße", "STRASSE");
+        check("lower", "ΟΔΟΣ", "οδος");
+        check("title", "hello wORLD-foo", "Hello World-Foo");
+        check("title", "ǉubav", "ǈubav");
+        check("snake", "Hello World", "hello_world");
+        check("snake", "myHTMLFile", "my_html_file");
+        check("snake", "  Привет, Мир!  ", "привет_мир");
+        check("kebab", "some_snake_case", "some-snake-case");
+        check("kebab", "file2Name", "file2-name");
+        check("camel", "hello big-world", "helloBigWorld");
+        check("camel", "ÉCOLE normale", "écoleNormale");
+        check("camel", "", "");
+        assert!(Modifier::parse("UPPER").is_none());
+    }
+}
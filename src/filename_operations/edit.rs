@@ -0,0 +1,244 @@
+//! Editing targets of [`ModifiedFilenames`] in a text editor, like `vidir` does
+//!
+//! Every file is a line `number<TAB>original name<TAB>target`. Only targets are edited,
+//! a removed line keeps its file in place
+use super::encoding::{from_bytes, to_bytes};
+use super::modified::{ModifiedFilename, ModifiedFilenames};
+use crate::errors::CommonError as Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+/// Comment written above the list of files
+const HEADER: &str = "# Edit targets in the last column, remove a line to keep its file in place\n\
+                      # number\toriginal name\ttarget\n";
+
+/// Returns the editor from `$VISUAL` or `$EDITOR`, `vi` if neither is set
+pub fn default_editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from("vi"))
+}
+
+/// Returns [`Error::InvalidEdit`] if the name cannot be written as a single line
+fn check_name(name: &[u8]) -> Result<(), Error> {
+    if name.contains(&b'\n') {
+        return Err(Error::InvalidEdit {
+            reason: format!(
+                "name {:?} contains a line break",
+                String::from_utf8_lossy(name)
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Writes files as numbered lines with their original names and targets
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidEdit`] if a name contains a line break
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsString;
+/// use mmv::filename_operations::edit::format_list;
+/// use mmv::filename_operations::modified::ModifiedFilename;
+/// let modified = vec![ModifiedFilename {
+///     origin: OsString::from("a.txt"),
+///     changed: OsString::from("a.md"),
+/// }];
+/// let list = String::from_utf8(format_list(&modified).unwrap()).unwrap();
+/// assert!(list.ends_with("\n1\ta.txt\ta.md\n"));
+/// ```
+pub fn format_list(modified: &ModifiedFilenames) -> Result<Vec<u8>, Error> {
+    let mut list = HEADER.as_bytes().to_vec();
+    for (index, filename) in modified.iter().enumerate() {
+        let (origin, changed) = (to_bytes(&filename.origin), to_bytes(&filename.changed));
+        check_name(&origin)?;
+        check_name(&changed)?;
+        list.extend(format!("{}\t", index + 1).as_bytes());
+        list.extend(origin.iter());
+        list.push(b'\t');
+        list.extend(changed.iter());
+        list.push(b'\n');
+    }
+    Ok(list)
+}
+
+/// Reads targets of the edited list back, files of removed lines are left out
+///
+/// Empty lines and lines starting with `#` are skipped
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidEdit`] if a line has an unknown or repeated number,
+/// a changed original name or an empty target
+pub fn parse_list(modified: ModifiedFilenames, list: &[u8]) -> Result<ModifiedFilenames, Error> {
+    let mut targets: Vec<Option<Vec<u8>>> = vec![None; modified.len()];
+    for (number, line) in list.split(|byte| *byte == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        let invalid = |reason: &str| Error::InvalidEdit {
+            reason: format!("line {}: {}", number + 1, reason),
+        };
+        let (index, rest) = line
+            .iter()
+            .position(|byte| *byte == b'\t')
+            .map(|tab| (&line[..tab], &line[tab + 1..]))
+            .ok_or_else(|| invalid("no file number"))?;
+        let index = std::str::from_utf8(index)
+            .ok()
+            .and_then(|index| index.trim().parse::<usize>().ok())
+            .filter(|index| (1..=modified.len()).contains(index))
+            .ok_or_else(|| invalid("unknown file number"))?
+            - 1;
+        let origin = to_bytes(&modified[index].origin);
+        let target = rest
+            .strip_prefix(origin.as_ref())
+            .and_then(|rest| rest.strip_prefix(b"\t"))
+            .ok_or_else(|| invalid("original name was changed"))?;
+        if target.is_empty() {
+            return Err(invalid("target is empty"));
+        }
+        if targets[index].replace(target.to_vec()).is_some() {
+            return Err(invalid("file is listed twice"));
+        }
+    }
+    Ok(modified
+        .into_iter()
+        .zip(targets)
+        .filter_map(|(filename, target)| {
+            Some(ModifiedFilename {
+                origin: filename.origin,
+                changed: from_bytes(target?),
+            })
+        })
+        .collect())
+}
+
+/// Creates a new file for the list in `directory`
+///
+/// Names that are taken, like files left by killed runs, are skipped with a numbered suffix
+fn create_list_file(directory: &Path) -> Result<(PathBuf, File), Error> {
+    let mut attempt = 0;
+    loop {
+        let name = match attempt {
+            0 => format!("mmv-edit-{}.txt", process::id()),
+            _ => format!("mmv-edit-{}-{}.txt", process::id(), attempt),
+        };
+        let path = directory.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => attempt += 1,
+            Err(error) => return Err(Error::IOError { error }),
+        }
+    }
+}
+
+/// Opens the editor on the file and waits for it to exit
+///
+/// The editor may be given with arguments, like `code --wait`. The command is split
+/// at whitespace without a shell, so quotes are not removed and the path of the editor
+/// cannot contain spaces
+fn run_editor(editor: &str, path: &Path) -> Result<(), Error> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|error| Error::IOError { error })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::EditorFailed {
+            editor: editor.to_string(),
+            status: status.to_string(),
+        })
+    }
+}
+
+/// Lets the user edit targets in the editor, see [`format_list`] and [`parse_list`]
+///
+/// The list is written to a new temporary file that is removed afterwards
+///
+/// # Errors
+///
+/// Propagates errors of [`format_list`] and [`parse_list`]
+///
+/// Returns [`Error::EditorFailed`] if the editor exits with an error and
+/// [`Error::IOError`] if the list could not be written, read or the editor not started
+pub fn edit_targets(modified: ModifiedFilenames, editor: &str) -> Result<ModifiedFilenames, Error> {
+    let list = format_list(&modified)?;
+    let (path, mut file) = create_list_file(&std::env::temp_dir())?;
+    let edited = file
+        .write_all(&list)
+        .map_err(|error| Error::IOError { error })
+        .and_then(|_| run_editor(editor, &path))
+        .and_then(|_| fs::read(&path).map_err(|error| Error::IOError { error }));
+    let _ = fs::remove_file(&path);
+    parse_list(modified, &edited?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_list_file, format_list, parse_list};
+    use crate::errors::CommonError as Error;
+    use crate::filename_operations::modified::{ModifiedFilename, ModifiedFilenames};
+    use crate::files::tests::FileManager;
+    use std::ffi::OsString;
+    use std::fs;
+
+    fn modified() -> ModifiedFilenames {
+        ["a.txt", "b\tc.txt", "d.txt"]
+            .into_iter()
+            .map(|name| ModifiedFilename {
+                origin: OsString::from(name),
+                changed: OsString::from(name.replace("txt", "md")),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn list_file_name_is_taken() {
+        let manager = FileManager::from("edit_list_file_name_is_taken");
+        let root = manager.get_directory();
+        let taken = root.join(format!("mmv-edit-{}.txt", std::process::id()));
+        fs::write(&taken, "stale").unwrap();
+        let (path, _) = create_list_file(root).unwrap();
+        assert_ne!(path, taken);
+        assert_eq!(fs::read_to_string(taken).unwrap(), "stale");
+        assert_eq!(fs::read_dir(root).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn edit_list() {
+        let list = String::from_utf8(format_list(&modified()).unwrap()).unwrap();
+        let edited = list
+            .replace("\ta.md", "\tnotes/a.md")
+            .replace("3\td.txt\td.md\n", "");
+        let parsed = parse_list(modified(), edited.as_bytes()).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].changed, "notes/a.md");
+        assert_eq!(parsed[1].origin, "b\tc.txt");
+        assert_eq!(parsed[1].changed, "b\tc.md");
+
+        for invalid in [
+            "4\ta.txt\tb",
+            "1\ta.md\tb",
+            "1\ta.txt\t",
+            "1\ta.txt\tb\n1\ta.txt\tc",
+        ] {
+            assert!(matches!(
+                parse_list(modified(), invalid.as_bytes()),
+                Err(Error::InvalidEdit { .. })
+            ));
+        }
+    }
+}
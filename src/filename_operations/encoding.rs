@@ -0,0 +1,48 @@
+//! Conversions between OS filenames and raw bytes
+//!
+//! On Unix filenames are arbitrary byte sequences, so they are matched and built without
+//! any loss. On other platforms filenames are converted through lossy UTF-8
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+
+/// Returns bytes of the filename
+#[cfg(unix)]
+pub fn to_bytes(filename: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(filename.as_bytes())
+}
+
+/// Returns bytes of the filename
+#[cfg(not(unix))]
+pub fn to_bytes(filename: &OsStr) -> Cow<'_, [u8]> {
+    match filename.to_string_lossy() {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    }
+}
+
+/// Builds filename from bytes
+#[cfg(unix)]
+pub fn from_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+/// Builds filename from bytes
+#[cfg(not(unix))]
+pub fn from_bytes(bytes: Vec<u8>) -> OsString {
+    OsString::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{from_bytes, to_bytes};
+
+    #[test]
+    fn bytes_round_trip() {
+        let bytes = vec![b'c', 0xe9, b'_', 0xff];
+        let filename = from_bytes(bytes.clone());
+        assert!(filename.to_str().is_none());
+        assert_eq!(to_bytes(&filename).into_owned(), bytes);
+    }
+}
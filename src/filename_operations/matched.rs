@@ -1,19 +1,27 @@
 //! Utils to match filenames by pattern
+use super::encoding::{from_bytes, to_bytes};
+use super::pattern::Pattern;
+use std::collections::HashMap;
 use std::ffi::OsString;
+use std::path::Path;
 
 /// Filename data after matching
 ///
 /// Contains original filaname and fragmented parts
 ///
+/// Fragments are kept as [`OsString`], so filenames that are not valid UTF-8 are carried through
+///
 /// # Examples
 /// if filename = "foo_bar" and pattern = "\*_\*"
 /// [`MatchedFilename`] will have the following structure:
 /// ```
+/// use std::collections::HashMap;
 /// use std::ffi::OsString;
 /// use mmv::filename_operations::matched::MatchedFilename;
 /// MatchedFilename {
 ///     filename: OsString::from("foo_bar"),
-///     fragments: vec![String::from("foo"), String::from("bar")]
+///     fragments: vec![OsString::from("foo"), OsString::from("bar")],
+///     named: HashMap::new(),
 /// };
 /// ```
 #[derive(Debug, PartialEq, Clone)]
@@ -21,7 +29,9 @@ pub struct MatchedFilename {
     /// Original filename
     pub filename: OsString,
     /// Extracted fragments according to pattern
-    pub fragments: Vec<String>,
+    pub fragments: Vec<OsString>,
+    /// Indices of fragments captured by named groups in regex mode
+    pub named: HashMap<String, usize>,
 }
 
 /// container (vector) of [`MatchedFilename`]
@@ -32,16 +42,18 @@ impl MatchedFilename {
         Self {
             filename,
             fragments: vec![],
+            named: HashMap::new(),
         }
     }
 }
 
 /// Returns [`MatchedFilename`] according to pattern
 ///
-/// Returns [`None`] if could not match pattern
+/// Returns [`None`] if could not match pattern or pattern is invalid
 ///
 /// # Examples
 /// ```
+/// use std::collections::HashMap;
 /// use std::ffi::OsString;
 /// use mmv::filename_operations::matched::{MatchedFilename, match_filename};
 /// let filename = OsString::from("filename");
@@ -51,7 +63,8 @@ impl MatchedFilename {
 ///     match_filename(&filename, pattern_good).unwrap(),
 ///     MatchedFilename {
 ///         filename: filename.clone(),
-///         fragments: vec![String::from("lena")]
+///         fragments: vec![OsString::from("lena")],
+///         named: HashMap::new(),
 ///     }
 /// );
 /// assert!(
@@ -59,30 +72,42 @@ impl MatchedFilename {
 /// );
 /// ```
 pub fn match_filename(filename: &OsString, pattern: &str) -> Option<MatchedFilename> {
-    let mut matched = MatchedFilename::new(filename.clone());
-    let mut filename_suffix = filename.to_str().unwrap();
-    let match_sequence: Vec<&str> = pattern.split("*").collect();
+    match_filename_with(filename, &Pattern::parse(pattern).ok()?)
+}
 
-    for i in 0..match_sequence.len() {
-        let mut string_to_match = match_sequence[i];
-        filename_suffix = filename_suffix.strip_prefix(string_to_match)?;
-        if i + 1 == match_sequence.len() {
-            break;
-        }
-        string_to_match = match_sequence[i + 1];
-        let mut split_index = filename_suffix.find(string_to_match)?;
-        if string_to_match == "" && split_index == 0 {
-            split_index = filename_suffix.len();
-        }
-        let mut fragment: &str = &"";
-        (fragment, filename_suffix) = filename_suffix.split_at(split_index);
-        matched.fragments.push(fragment.to_string());
-    }
-    if filename_suffix.is_empty() {
-        Some(matched)
-    } else {
-        None
-    }
+/// Returns [`MatchedFilename`] according to already parsed [`Pattern`]
+///
+/// Returns [`None`] if could not match pattern
+pub fn match_filename_with(filename: &OsString, pattern: &Pattern) -> Option<MatchedFilename> {
+    let fragments = pattern.match_bytes(&to_bytes(filename))?;
+    Some(MatchedFilename {
+        filename: filename.clone(),
+        fragments: fragments.into_iter().map(from_bytes).collect(),
+        named: pattern.capture_names(),
+    })
+}
+
+/// Extends candidate found in a directory tree by matching its filename against [`Pattern`]
+///
+/// Candidate filename may be a relative path, only its last component is matched.
+/// Fragments of the filename are appended after fragments already stored in the candidate
+///
+/// Returns [`None`] if could not match pattern
+pub fn match_candidate(candidate: MatchedFilename, pattern: &Pattern) -> Option<MatchedFilename> {
+    let filename = Path::new(&candidate.filename).file_name()?.to_os_string();
+    let matched = match_filename_with(&filename, pattern)?;
+    let offset = candidate.fragments.len();
+    let mut fragments = candidate.fragments;
+    fragments.extend(matched.fragments);
+    Some(MatchedFilename {
+        filename: candidate.filename,
+        fragments,
+        named: matched
+            .named
+            .into_iter()
+            .map(|(name, index)| (name, index + offset))
+            .collect(),
+    })
 }
 
 #[cfg(test)]
@@ -104,6 +129,9 @@ mod tests {
         match_filename_ok("A_*", "A_B", vec!["B"]);
         match_filename_ok("A_B", "A_B", vec![]);
         match_filename_ok("*_*_*", "ABA_CA_BA", vec!["ABA", "CA", "BA"]);
+        match_filename_ok("*.txt", "report.txt.txt", vec!["report.txt"]);
+        match_filename_ok("*_b", "a_b_b", vec!["a_b"]);
+        match_filename_ok("*+_*", "a_b_c", vec!["a_b", "c"]);
     }
 
     #[test]
@@ -116,4 +144,19 @@ mod tests {
         match_filename_none("123", "1234");
         match_filename_none("123", "4123");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn match_filename_non_unicode() {
+        use std::os::unix::ffi::OsStringExt;
+        let filename = OsString::from_vec(vec![b'c', 0xe9, b'_', b'1', b'.', 0xff]);
+        let matched = match_filename(&filename, "*_*").unwrap();
+        assert_eq!(
+            matched.fragments,
+            vec![
+                OsString::from_vec(vec![b'c', 0xe9]),
+                OsString::from_vec(vec![b'1', b'.', 0xff])
+            ]
+        );
+    }
 }
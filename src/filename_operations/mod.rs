@@ -1,3 +1,7 @@
+pub mod case;
+pub mod edit;
+pub mod encoding;
 pub mod matched;
 pub mod modified;
 pub mod operations;
+pub mod pattern;
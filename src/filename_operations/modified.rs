@@ -1,21 +1,100 @@
 //! Utils to modify filenames by pattern
+use super::case::Modifier;
+use super::encoding::{from_bytes, to_bytes};
 use super::matched::MatchedFilename;
 use crate::errors::CommonError as Error;
 use regex::{Captures, Regex};
+use std::collections::HashMap;
 use std::ffi::OsString;
 
+/// Original filename and the filename it should be changed to
+///
+/// Both names may contain bytes that are not valid UTF-8
 pub struct ModifiedFilename {
     pub origin: OsString,
-    pub changed: String,
+    pub changed: OsString,
 }
 
 /// container(vector) of [`ModifiedFilename`]
 pub type ModifiedFilenames = Vec<ModifiedFilename>;
 
+/// Returns [`Regex`] that detects `#N` and `#{name}` markers with their modifiers
+///
+/// Modifiers go inside braces like `#{1:upper}` and `#{name:upper}`
+/// or follow a marker like `#1:upper` and `#{name}:upper`
+pub fn markers_regex() -> Regex {
+    Regex::new(r"#(?:([0-9]+)|\{([^{}:]*)((?::[^{}:]*)*)\})((?::[A-Za-z]+)*)").unwrap()
+}
+
+/// Returns modifiers of the marker in order of application and the end of the marker
+///
+/// Inside braces every modifier must be known. Modifiers that follow a marker end
+/// at the first unknown name, so `#1:v2` is the fragment followed by the text `:v2`
+///
+/// # Errors
+/// Returns [`Error::UnknownModifier`] with 1-based character position of the modifier
+/// in pattern
+fn marker_modifiers(marker: &Captures, pattern: &str) -> Result<(Vec<Modifier>, usize), Error> {
+    let mut parsed = vec![];
+    if let Some(modifiers) = marker.get(3) {
+        let mut start = modifiers.start();
+        for name in modifiers.as_str().split(':').skip(1) {
+            start += 1;
+            parsed.push(Modifier::parse(name).ok_or_else(|| Error::UnknownModifier {
+                modifier: name.to_string(),
+                position: pattern[..start].chars().count() + 1,
+            })?);
+            start += name.len();
+        }
+    }
+    let following = marker.get(4).unwrap();
+    let mut end = following.start();
+    for name in following.as_str().split(':').skip(1) {
+        let Some(modifier) = Modifier::parse(name) else {
+            break;
+        };
+        parsed.push(modifier);
+        end += name.len() + 1;
+    }
+    Ok((parsed, end))
+}
+
+/// Returns zero-based index of the fragment with 1-based `number`
+fn number_index(number: &str, marker_max: usize) -> Result<usize, Error> {
+    match number.parse::<usize>() {
+        Err(_) => Err(Error::InvalidMarkersValue),
+        Ok(number) if number == 0 || number > marker_max => Err(Error::TooBigMarkerValue),
+        Ok(number) => Ok(number - 1),
+    }
+}
+
+/// Returns zero-based index of the fragment referenced by marker
+///
+/// # Errors
+/// Same as [`check_markers_correctness`]
+fn marker_index(
+    marker: &Captures,
+    marker_max: usize,
+    named: &HashMap<String, usize>,
+) -> Result<usize, Error> {
+    let Some(reference) = marker.get(2) else {
+        return number_index(&marker[1], marker_max);
+    };
+    let reference = reference.as_str();
+    if !reference.is_empty() && reference.bytes().all(|byte| byte.is_ascii_digit()) {
+        return number_index(reference, marker_max);
+    }
+    named
+        .get(reference)
+        .copied()
+        .ok_or_else(|| Error::UnknownMarkerName {
+            name: reference.to_string(),
+        })
+}
+
 /// Returns Ok(()) if all markers in pattern are correct
 ///
-/// Takes &[`Regex`] to detect markers
-/// // TODO any marker detection, currently only task-specified is implemented
+/// Takes &[`Regex`] to detect markers, see [`markers_regex`]
 ///
 /// # Errors
 /// Returns [`Error::InvalidMarkersValue`] if a marker cannot be parsed into usize
@@ -23,43 +102,74 @@ pub type ModifiedFilenames = Vec<ModifiedFilename>;
 /// Returns [`Error::TooBigMarkerValue`] if a marker was succesfully parsed
 /// but is equals 0 or greater than marker_max
 ///
+/// Returns [`Error::UnknownMarkerName`] if a `#{name}` marker is not in `named`
+///
+/// Returns [`Error::UnknownModifier`] if a marker has an unknown modifier inside braces
+///
 /// # Examples
 ///
 /// ```
-/// use regex::Regex;
-/// use mmv::filename_operations::modified::check_markers_correctness;
-/// let regex = Regex::new("#([0-9]+)").unwrap();
-/// let pattern_good = "#1 #2 #3";
+/// use std::collections::HashMap;
+/// use mmv::filename_operations::modified::{check_markers_correctness, markers_regex};
+/// let regex = markers_regex();
+/// let named = HashMap::from([(String::from("year"), 0)]);
+/// let pattern_good = "#1 #2 #3 #{year}";
 /// let pattern_bad = "#0 #5";
 /// assert!(
-///   check_markers_correctness(&regex, pattern_good, 3).is_ok()
+///   check_markers_correctness(&regex, pattern_good, 3, &named).is_ok()
+/// );
+/// assert!(
+///   check_markers_correctness(&regex, pattern_bad, 3, &named).is_err()
+/// );
+/// assert!(
+///   check_markers_correctness(&regex, "#{month}", 3, &named).is_err()
 /// );
 /// assert!(
-///   check_markers_correctness(&regex, pattern_bad, 3).is_err()
+///   check_markers_correctness(&regex, "#1:upper #{2:shout}", 3, &named).is_err()
 /// );
 /// ```
 pub fn check_markers_correctness(
     regex: &Regex,
     pattern: &str,
     marker_max: usize,
+    named: &HashMap<String, usize>,
 ) -> Result<(), Error> {
-    for digits in regex.find_iter(pattern) {
-        match digits.as_str().strip_prefix("#").unwrap().parse::<usize>() {
-            Err(_) => return Err(Error::InvalidMarkersValue),
-            Ok(marker) if marker == 0 || marker > marker_max => {
-                return Err(Error::TooBigMarkerValue);
-            }
-            Ok(_) => {}
-        }
+    for marker in regex.captures_iter(pattern) {
+        marker_index(&marker, marker_max, named)?;
+        marker_modifiers(&marker, pattern)?;
     }
     Ok(())
 }
 
+/// Removes empty components of a `/`-separated path
+///
+/// Empty fragments such as `**` matching no directories must not turn `#1/#2` into
+/// an absolute path
+fn drop_empty_components(path: &[u8]) -> Vec<u8> {
+    path.split(|&byte| byte == b'/')
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join(&b'/')
+}
+
+/// Replaces escaped `\:` in text of the pattern with `:`
+fn unescape(text: &str) -> String {
+    text.replace("\\:", ":")
+}
+
 /// Modifies [`MatchedFilename`] into [`ModifiedFilename`] according to pattern
 ///
+/// `#N` and `#{N}` markers are replaced with N-th fragment, `#{name}` markers with
+/// the fragment of the named group. Modifiers like `#1:upper` or `#{name:snake}` convert
+/// the case of the fragment, see [`Modifier`]. `\:` is replaced with `:` everywhere,
+/// so `#1\:upper` keeps `:upper` as text. Empty path components of the result are dropped
+///
 /// # Errors
 ///
-/// All errors are handled in [`check_markers_correctness`] and then propagated higher
+/// Marker errors are handled in [`check_markers_correctness`] and then propagated higher
+///
+/// Returns [`Error::NonUnicodeName`] if a modifier is applied to a fragment
+/// that is not valid UTF-8
 ///
 /// # Examples
 /// ```
@@ -70,28 +180,49 @@ pub fn check_markers_correctness(
 /// assert!(modify_filename(matched_name, "#0 new_name").is_err());
 /// ```
 pub fn modify_filename(matched: MatchedFilename, pattern: &str) -> Result<ModifiedFilename, Error> {
-    let regex = Regex::new("#([0-9]+)").unwrap();
+    let regex = markers_regex();
     let marker_max_value = matched.fragments.len();
-    check_markers_correctness(&regex, pattern, marker_max_value)?;
-    let changed_filename = Regex::replace_all(&regex, pattern, |digits: &Captures| -> &str {
-        let marker = digits[0]
-            .strip_prefix("#")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-        &matched.fragments[marker - 1]
-    });
+    check_markers_correctness(&regex, pattern, marker_max_value, &matched.named)?;
+    let mut changed_filename: Vec<u8> = vec![];
+    let mut last_end = 0;
+    for marker in regex.captures_iter(pattern) {
+        let index = marker_index(&marker, marker_max_value, &matched.named)?;
+        let (modifiers, end) = marker_modifiers(&marker, pattern)?;
+        let fragment = &matched.fragments[index];
+        changed_filename.extend_from_slice(
+            unescape(&pattern[last_end..marker.get(0).unwrap().start()]).as_bytes(),
+        );
+        if modifiers.is_empty() {
+            changed_filename.extend_from_slice(&to_bytes(fragment));
+        } else {
+            let mut text = fragment
+                .to_str()
+                .ok_or_else(|| Error::NonUnicodeName {
+                    name: fragment.to_string_lossy().into_owned(),
+                })?
+                .to_string();
+            for modifier in modifiers {
+                text = modifier.apply(&text);
+            }
+            changed_filename.extend_from_slice(text.as_bytes());
+        }
+        last_end = end;
+    }
+    changed_filename.extend_from_slice(unescape(&pattern[last_end..]).as_bytes());
     Ok(ModifiedFilename {
         origin: matched.filename,
-        changed: changed_filename.to_string(),
+        changed: from_bytes(drop_empty_components(&changed_filename)),
     })
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::errors::CommonError as Error;
     use crate::filename_operations::matched::MatchedFilename;
-    use crate::filename_operations::modified::{check_markers_correctness, modify_filename};
-    use regex::Regex;
+    use crate::filename_operations::modified::{
+        check_markers_correctness, markers_regex, modify_filename,
+    };
+    use std::collections::HashMap;
     use std::ffi::OsString;
 
     #[test]
@@ -106,10 +237,8 @@ mod tests {
             let modified = modify_filename(
                 MatchedFilename {
                     filename: filename.clone(),
-                    fragments: fragments
-                        .into_iter()
-                        .map(|fragment| fragment.to_string())
-                        .collect(),
+                    fragments: fragments.into_iter().map(OsString::from).collect(),
+                    named: HashMap::from([(String::from("first"), 0)]),
                 },
                 pattern,
             )
@@ -124,16 +253,77 @@ mod tests {
             "#3_#2_#1",
             "***_pahnut_money",
         );
+        modify_filename_ok("a_b", vec!["a", "b"], "#2_#{first}", "b_a");
+        modify_filename_ok("a_b", vec!["a", "b"], "#{first}#{first}", "aa");
+        modify_filename_ok("_b", vec!["", "b"], "#1/#2", "b");
+        modify_filename_ok("a_b", vec!["a", "b"], "#1//#2/", "a/b");
+        modify_filename_ok(
+            "a_b",
+            vec!["my file", "b"],
+            "#1:snake.#2:upper",
+            "my_file.B",
+        );
+        modify_filename_ok("a_b", vec!["my file", "b"], "#{first}:title", "My File");
+        modify_filename_ok("a_b", vec!["my file", "b"], "#1:upper:kebab", "my-file");
+        modify_filename_ok("a_b", vec!["a", "b"], "#1:2", "a:2");
+        modify_filename_ok("a_b", vec!["a", "b"], "#1:v2", "a:v2");
+        modify_filename_ok("a_b", vec!["a", "b"], "#1:upper:v2", "A:v2");
+        modify_filename_ok("a_b", vec!["a", "b"], "#1\\:upper", "a:upper");
+        modify_filename_ok("a_b", vec!["a", "b"], "#{1:upper}:v2", "A:v2");
+        modify_filename_ok("a_b", vec!["my file", "b"], "#{first:snake}", "my_file");
+        modify_filename_ok("a_b", vec!["a", "b"], "#{first}\\:b\\:c", "a:b:c");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn modify_filename_non_unicode() {
+        use std::os::unix::ffi::OsStringExt;
+        let fragment = OsString::from_vec(vec![b'c', 0xe9]);
+        let modified = modify_filename(
+            MatchedFilename {
+                filename: OsString::from_vec(vec![b'c', 0xe9, b'.', b'a']),
+                fragments: vec![fragment, OsString::from("a")],
+                named: HashMap::new(),
+            },
+            "#2_#1.txt",
+        )
+        .unwrap();
+        assert_eq!(
+            modified.changed,
+            OsString::from_vec(vec![b'a', b'_', b'c', 0xe9, b'.', b't', b'x', b't'])
+        );
+        let error = modify_filename(
+            MatchedFilename {
+                filename: OsString::from_vec(vec![b'c', 0xe9]),
+                fragments: vec![OsString::from_vec(vec![b'c', 0xe9])],
+                named: HashMap::new(),
+            },
+            "#1:upper",
+        );
+        assert!(matches!(error, Err(Error::NonUnicodeName { .. })));
     }
 
     #[test]
     fn marker_correctness_checker() {
-        let regex = Regex::new("#([0-9]+)").unwrap();
-        let check = |pattern, marker_max| check_markers_correctness(&regex, pattern, marker_max);
+        let regex = markers_regex();
+        let named = HashMap::from([(String::from("name"), 0)]);
+        let check =
+            |pattern, marker_max| check_markers_correctness(&regex, pattern, marker_max, &named);
         assert!(check("memi", 0).is_ok());
         assert!(check("#1 228", 1).is_ok());
         assert!(check("#aboba", 0).is_ok());
         assert!(check("#-123", 0).is_ok());
         assert!(check("#123", 1).is_err());
+        assert!(check("#{name}", 1).is_ok());
+        assert!(check("#{other}", 1).is_err());
+        assert!(check("#{name", 1).is_ok());
+        assert!(check("#1:lower:camel", 1).is_ok());
+        assert!(check("#1:v2", 1).is_ok());
+        assert!(check("#{1:v2}", 1).is_err());
+        assert!(check("#{2}", 1).is_err());
+        assert!(matches!(
+            check("ж_#1:upper_#{name:lower:shout}", 1),
+            Err(Error::UnknownModifier { modifier, position: 25 }) if modifier == "shout"
+        ));
     }
 }
@@ -1,8 +1,8 @@
 //! Utils to [`match_filenames`] and [`modify_filenames`]
-use super::matched::{match_filename, MatchedFilenames};
+use super::matched::{match_candidate, MatchedFilenames};
+use super::pattern::Pattern;
 use crate::errors::CommonError as Error;
 use crate::filename_operations::modified::{modify_filename, ModifiedFilenames};
-use std::ffi::OsString;
 
 /// Modifies [`MatchedFilenames`] according to pattern
 ///
@@ -17,7 +17,7 @@ pub fn modify_filenames(
 ) -> Result<ModifiedFilenames, Error> {
     let mut modified: ModifiedFilenames = vec![];
     for matched_filename in matched {
-        let modified_value = modify_filename(matched_filename, &pattern)?;
+        let modified_value = modify_filename(matched_filename, pattern)?;
         modified.push(modified_value);
     }
     Ok(modified)
@@ -25,21 +25,25 @@ pub fn modify_filenames(
 
 /// Mathches filenames according to pattern
 ///
-/// Iterates over filenames and converts them into [`MatchedFilenames`]
-/// using [`match_filename`] function
+/// Iterates over candidates and keeps those whose filename matches
+/// using [`match_candidate`] function. Candidates may already contain fragments
+/// captured by directory components of the input template
 ///
 /// # Errors
 /// Returns [`Error::NoMatchingFiles`] if resulting [`MatchedFilenames`] is empty
-pub fn match_filenames(filenames: Vec<OsString>, pattern: &str) -> Result<MatchedFilenames, Error> {
+pub fn match_filenames(
+    candidates: MatchedFilenames,
+    pattern: &Pattern,
+) -> Result<MatchedFilenames, Error> {
     let mut matched: MatchedFilenames = vec![];
-    for filename in filenames {
-        if let Some(matched_value) = match_filename(&filename, &pattern) {
+    for candidate in candidates {
+        if let Some(matched_value) = match_candidate(candidate, pattern) {
             matched.push(matched_value)
         }
     }
     if matched.is_empty() {
         Err(Error::NoMatchingFiles {
-            pattern: pattern.to_string(),
+            pattern: pattern.as_str().to_string(),
         })
     } else {
         Ok(matched)
@@ -51,17 +55,19 @@ pub fn match_filenames(filenames: Vec<OsString>, pattern: &str) -> Result<Matche
 /// # Errors
 /// Propagates errors from according functions
 pub fn match_and_modify_filenames(
-    filenames: Vec<OsString>,
-    input_pattern: &str,
+    candidates: MatchedFilenames,
+    input_pattern: &Pattern,
     output_pattern: &str,
 ) -> Result<ModifiedFilenames, Error> {
-    let matched_filenames = match_filenames(filenames, input_pattern)?;
-    Ok(modify_filenames(matched_filenames, output_pattern)?)
+    let matched_filenames = match_filenames(candidates, input_pattern)?;
+    modify_filenames(matched_filenames, output_pattern)
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::filename_operations::matched::MatchedFilename;
     use crate::filename_operations::operations::match_and_modify_filenames;
+    use crate::filename_operations::pattern::Pattern;
     use std::ffi::OsString;
 
     #[test]
@@ -72,17 +78,57 @@ mod tests {
             output_pattern: &str,
             expected: &str,
         ) {
-            let filename = OsString::from(filename.to_string());
+            let candidate = MatchedFilename::new(OsString::from(filename));
+            let input_pattern = Pattern::parse(input_pattern).unwrap();
             assert_eq!(
-                match_and_modify_filenames(vec![filename], input_pattern, output_pattern).unwrap()
-                    [0]
-                .changed,
+                match_and_modify_filenames(vec![candidate], &input_pattern, output_pattern)
+                    .unwrap()[0]
+                    .changed,
                 expected
             );
         }
 
         match_and_modify_ok("sus_amogus", "*_*", "#2_#1", "amogus_sus");
         match_and_modify_ok("biba_boba_buba", "*_*_*", "#3-#1-#2", "buba-biba-boba");
-        match_and_modify_ok("bruh", "*", "#1", "bruh")
+        match_and_modify_ok("bruh", "*", "#1", "bruh");
+        match_and_modify_ok("log.txt.txt", "*.txt", "#1", "log.txt");
+        match_and_modify_ok(
+            "scan_0042_a.tif",
+            "scan_[0-9][0-9]??_[!0-9].tif",
+            "#5_#1#2#3#4.tif",
+            "a_0042.tif",
+        );
+    }
+
+    #[test]
+    fn match_and_modify_regex() {
+        let input_pattern = Pattern::parse_regex(r"(?<date>\d{4}-\d{2})_(\w+)\.jpe?g").unwrap();
+        let modified = match_and_modify_filenames(
+            vec![
+                MatchedFilename::new(OsString::from("2023-05_trip.jpeg")),
+                MatchedFilename::new(OsString::from("trip.jpg")),
+            ],
+            &input_pattern,
+            "#2_#{date}.jpg",
+        )
+        .unwrap();
+        assert_eq!(modified.len(), 1);
+        assert_eq!(modified[0].changed, "trip_2023-05.jpg");
+    }
+
+    #[test]
+    fn match_and_modify_nested() {
+        let candidate = MatchedFilename {
+            fragments: vec![OsString::from("2023/jan")],
+            ..MatchedFilename::new(OsString::from("2023/jan/IMG_1.jpg"))
+        };
+        let modified = match_and_modify_filenames(
+            vec![candidate],
+            &Pattern::parse("IMG_*.jpg").unwrap(),
+            "#1/#2.jpg",
+        )
+        .unwrap();
+        assert_eq!(modified[0].origin, "2023/jan/IMG_1.jpg");
+        assert_eq!(modified[0].changed, "2023/jan/1.jpg");
     }
 }
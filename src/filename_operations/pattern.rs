@@ -0,0 +1,807 @@
+//! Parsing and backtracking matching of filename patterns
+//!
+//! Pattern grammar:
+//! - `*` matches any sequence of characters, shortest candidates are tried first
+//! - `*+` matches any sequence of characters, longest candidates are tried first
+//! - `?` matches exactly one character
+//! - `[abc]` matches one of the listed characters, `[a-z]` matches one character of the range
+//! - `[!0-9]` matches one character that is not listed
+//! - `{jpg,jpeg,png}` matches one of the comma-separated alternatives, which may contain
+//!   any other wildcards including nested braces
+//! - `\` escapes the following character, so `\*`, `\?`, `\[`, `\{`, `\,` and `\}` match
+//!   the character literally
+//!
+//! Inside a class `]` is literal when it goes first, `-` is literal when it goes first or last,
+//! and `\` escapes any character. Outside of braces `,` and `}` are literal.
+//!
+//! Every wildcard produces a fragment, numbered from left to right by its opening character.
+//! A brace group captures the whole chosen alternative, wildcards inside alternatives
+//! that were not chosen produce empty fragments
+//!
+//! Alternatively a pattern can be a regular expression, see [`Pattern::parse_regex`]
+//!
+//! With [`PatternOptions::ignore_case`] text is compared after Unicode case folding,
+//! while fragments keep the original case
+use crate::errors::CommonError as Error;
+use regex::bytes::Regex;
+use regex_syntax::ParserBuilder;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// How a `*` wildcard chooses between several valid captures
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Greed {
+    /// Shortest capture is tried first (`*`)
+    Lazy,
+    /// Longest capture is tried first (`*+`)
+    Greedy,
+}
+
+/// Single element of a parsed [`Pattern`]
+#[derive(Debug, PartialEq, Clone)]
+enum Node {
+    /// Text that must be matched exactly
+    Literal(String),
+    /// Any sequence of characters, stored into fragment `index`
+    Star { greed: Greed, index: usize },
+    /// Exactly one character, stored into fragment `index`
+    AnyChar { index: usize },
+    /// Exactly one character from the class, stored into fragment `index`
+    Class { class: CharClass, index: usize },
+    /// One of the alternatives, stored into fragment `index`
+    Alternation {
+        alternatives: Vec<Vec<Node>>,
+        index: usize,
+    },
+}
+
+/// Set of characters described by `[...]`
+#[derive(Debug, PartialEq, Clone)]
+struct CharClass {
+    /// Inclusive ranges of characters, single characters are stored as `(c, c)`
+    ranges: Vec<(char, char)>,
+    /// `true` for `[!...]`
+    negated: bool,
+}
+
+impl CharClass {
+    /// Parses class body after the opening `[`, consuming the closing `]`
+    fn parse(chars: &mut Peekable<Chars>) -> Result<Self, Error> {
+        let negated = chars.next_if_eq(&'!').is_some();
+        let mut ranges = vec![];
+        let mut first = true;
+        loop {
+            let start = match chars.next().ok_or(Error::InvalidNamePattern)? {
+                ']' if !first => break,
+                '\\' => chars.next().ok_or(Error::InvalidNamePattern)?,
+                symbol => symbol,
+            };
+            first = false;
+            let mut end = start;
+            if chars.peek() == Some(&'-') {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                match lookahead.next() {
+                    None => return Err(Error::InvalidNamePattern),
+                    Some(']') => {}
+                    Some(symbol) => {
+                        end = if symbol == '\\' {
+                            lookahead.next().ok_or(Error::InvalidNamePattern)?
+                        } else {
+                            symbol
+                        };
+                        *chars = lookahead;
+                    }
+                }
+            }
+            if start > end {
+                return Err(Error::InvalidNamePattern);
+            }
+            ranges.push((start, end));
+        }
+        Ok(Self { ranges, negated })
+    }
+
+    /// Returns `true` if character belongs to the class
+    ///
+    /// With `ignore_case` lowercase and uppercase forms of the character are checked too.
+    /// Bytes that are not valid UTF-8 are passed as [`None`] and only match negated classes
+    fn contains(&self, symbol: Option<char>, ignore_case: bool) -> bool {
+        let in_ranges = |symbol: char| {
+            self.ranges
+                .iter()
+                .any(|&(start, end)| start <= symbol && symbol <= end)
+        };
+        let listed = symbol.is_some_and(|symbol| {
+            in_ranges(symbol)
+                || ignore_case
+                    && symbol
+                        .to_lowercase()
+                        .chain(symbol.to_uppercase())
+                        .any(in_ranges)
+        });
+        listed != self.negated
+    }
+}
+
+/// Returns case-folded form of the character
+///
+/// Characters are uppercased and then lowercased, so `ß`, `ẞ` and `SS` fold to `ss`,
+/// and `ς`, `σ` and `Σ` fold to `σ`
+fn fold_case(symbol: char) -> impl Iterator<Item = char> {
+    symbol.to_uppercase().flat_map(char::to_lowercase)
+}
+
+/// Options that change how a [`Pattern`] is parsed and matched
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PatternOptions {
+    /// Parse pattern as a regular expression, see [`Pattern::parse_regex`]
+    pub regex: bool,
+    /// Compare text without regard to case
+    pub ignore_case: bool,
+}
+
+/// Matching engine of a [`Pattern`]
+#[derive(Debug, Clone)]
+enum Engine {
+    /// Backtracking glob matcher
+    Glob(Vec<Node>),
+    /// Regular expression anchored against the whole filename
+    Regex(Regex),
+}
+
+/// Parsed filename pattern
+///
+/// # Examples
+/// ```
+/// use mmv::filename_operations::pattern::Pattern;
+/// let pattern = Pattern::parse("*.txt").unwrap();
+/// assert_eq!(pattern.captures_count(), 1);
+/// assert_eq!(
+///     pattern.match_bytes(b"report.txt.txt").unwrap(),
+///     vec![b"report.txt".to_vec()]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    engine: Engine,
+    captures_count: usize,
+    ignore_case: bool,
+}
+
+/// Byte ranges of captured fragments, indexed by wildcard number
+type Captures = Vec<Option<(usize, usize)>>;
+
+impl Pattern {
+    /// Parses pattern string into [`Pattern`]
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidNamePattern`] if pattern ends with unpaired `\`,
+    /// has unclosed `[` or `{`, or a reversed range like `[z-a]`
+    pub fn parse(pattern: &str) -> Result<Self, Error> {
+        Self::parse_with(pattern, &PatternOptions::default())
+    }
+
+    /// Parses pattern string into [`Pattern`] according to [`PatternOptions`]
+    ///
+    /// # Errors
+    /// Same as [`Pattern::parse`] or [`Pattern::parse_regex`] depending on options
+    ///
+    /// # Examples
+    /// ```
+    /// use mmv::filename_operations::pattern::{Pattern, PatternOptions};
+    /// let options = PatternOptions {
+    ///     ignore_case: true,
+    ///     ..Default::default()
+    /// };
+    /// let pattern = Pattern::parse_with("*.jpg", &options).unwrap();
+    /// assert_eq!(pattern.match_bytes(b"A.JPG").unwrap(), vec![b"A".to_vec()]);
+    /// ```
+    pub fn parse_with(pattern: &str, options: &PatternOptions) -> Result<Self, Error> {
+        if options.regex {
+            return Self::compile_regex(pattern, options.ignore_case);
+        }
+        let mut captures_count = 0;
+        let mut chars = pattern.chars().peekable();
+        let (mut nodes, _) = Self::parse_sequence(&mut chars, &mut captures_count, false)?;
+        if options.ignore_case {
+            fold_literals(&mut nodes);
+        }
+        Ok(Self {
+            source: pattern.to_string(),
+            engine: Engine::Glob(nodes),
+            captures_count,
+            ignore_case: options.ignore_case,
+        })
+    }
+
+    /// Parses regular expression into [`Pattern`]
+    ///
+    /// The expression is anchored against the whole filename.
+    /// Numbered groups produce fragments, named groups can also be referenced by name
+    ///
+    /// # Errors
+    /// Returns [`Error::InvalidRegex`] describing the part of the expression that failed to compile
+    ///
+    /// # Examples
+    /// ```
+    /// use mmv::filename_operations::pattern::Pattern;
+    /// let pattern = Pattern::parse_regex(r"(?<name>\w+)_(\d+)").unwrap();
+    /// assert_eq!(
+    ///     pattern.match_bytes(b"scan_42").unwrap(),
+    ///     vec![b"scan".to_vec(), b"42".to_vec()]
+    /// );
+    /// assert!(pattern.match_bytes(b"scan_42.tif").is_none());
+    /// assert!(Pattern::parse_regex("(scan").is_err());
+    /// ```
+    pub fn parse_regex(pattern: &str) -> Result<Self, Error> {
+        Self::compile_regex(pattern, false)
+    }
+
+    /// Compiles the expression anchored against the whole filename
+    ///
+    /// The anchors are put around the expression printed back from its syntax tree,
+    /// so flags and `(?x)` comments of the expression cannot swallow them
+    fn compile_regex(pattern: &str, ignore_case: bool) -> Result<Self, Error> {
+        let invalid_regex = |error: regex::Error| Error::InvalidRegex {
+            error: error.to_string(),
+        };
+        Regex::new(pattern).map_err(invalid_regex)?;
+        let syntax = ParserBuilder::new()
+            .utf8(false)
+            .case_insensitive(ignore_case)
+            .build()
+            .parse(pattern)
+            .map_err(|error| Error::InvalidRegex {
+                error: error.to_string(),
+            })?;
+        let regex = Regex::new(&format!("^(?:{})$", syntax)).map_err(invalid_regex)?;
+        Ok(Self {
+            source: pattern.to_string(),
+            captures_count: regex.captures_len() - 1,
+            engine: Engine::Regex(regex),
+            ignore_case,
+        })
+    }
+
+    /// Returns pattern as it was written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns indices of fragments produced by named groups
+    pub fn capture_names(&self) -> HashMap<String, usize> {
+        match &self.engine {
+            Engine::Glob(_) => HashMap::new(),
+            Engine::Regex(regex) => regex
+                .capture_names()
+                .enumerate()
+                .filter_map(|(group, name)| Some((name?.to_string(), group - 1)))
+                .collect(),
+        }
+    }
+
+    /// Parses nodes until the end of pattern or, inside braces, until `,` or `}`
+    ///
+    /// Returns parsed nodes and the character that terminated the sequence
+    fn parse_sequence(
+        chars: &mut Peekable<Chars>,
+        captures_count: &mut usize,
+        nested: bool,
+    ) -> Result<(Vec<Node>, Option<char>), Error> {
+        let mut nodes: Vec<Node> = vec![];
+        let mut literal = String::new();
+        let mut terminator = None;
+        while let Some(symbol) = chars.next() {
+            match symbol {
+                ',' | '}' if nested => {
+                    terminator = Some(symbol);
+                    break;
+                }
+                '\\' => literal.push(chars.next().ok_or(Error::InvalidNamePattern)?),
+                '*' => {
+                    if !literal.is_empty() {
+                        nodes.push(Node::Literal(std::mem::take(&mut literal)));
+                    }
+                    let greed = if chars.next_if_eq(&'+').is_some() {
+                        Greed::Greedy
+                    } else {
+                        Greed::Lazy
+                    };
+                    nodes.push(Node::Star {
+                        greed,
+                        index: *captures_count,
+                    });
+                    *captures_count += 1;
+                }
+                '{' => {
+                    if !literal.is_empty() {
+                        nodes.push(Node::Literal(std::mem::take(&mut literal)));
+                    }
+                    let index = *captures_count;
+                    *captures_count += 1;
+                    let mut alternatives = vec![];
+                    loop {
+                        let (alternative, terminator) =
+                            Self::parse_sequence(chars, captures_count, true)?;
+                        alternatives.push(alternative);
+                        match terminator {
+                            Some(',') => {}
+                            Some(_) => break,
+                            None => return Err(Error::InvalidNamePattern),
+                        }
+                    }
+                    nodes.push(Node::Alternation {
+                        alternatives,
+                        index,
+                    });
+                }
+                '?' | '[' => {
+                    if !literal.is_empty() {
+                        nodes.push(Node::Literal(std::mem::take(&mut literal)));
+                    }
+                    let index = *captures_count;
+                    nodes.push(if symbol == '?' {
+                        Node::AnyChar { index }
+                    } else {
+                        Node::Class {
+                            class: CharClass::parse(chars)?,
+                            index,
+                        }
+                    });
+                    *captures_count += 1;
+                }
+                _ => literal.push(symbol),
+            }
+        }
+        if !literal.is_empty() {
+            nodes.push(Node::Literal(literal));
+        }
+        Ok((nodes, terminator))
+    }
+
+    /// Returns number of fragments produced by a successful match
+    pub fn captures_count(&self) -> usize {
+        self.captures_count
+    }
+
+    /// Matches the whole `input` against pattern
+    ///
+    /// Returns captured fragments or [`None`] if `input` does not match
+    pub fn match_bytes(&self, input: &[u8]) -> Option<Vec<Vec<u8>>> {
+        let nodes = match &self.engine {
+            Engine::Glob(nodes) => nodes,
+            Engine::Regex(regex) => {
+                let groups = regex.captures(input)?;
+                return Some(
+                    groups
+                        .iter()
+                        .skip(1)
+                        .map(|group| group.map_or(vec![], |group| group.as_bytes().to_vec()))
+                        .collect(),
+                );
+            }
+        };
+        let matcher = Matcher {
+            input,
+            ignore_case: self.ignore_case,
+            failed: RefCell::default(),
+            steps: Cell::default(),
+        };
+        let mut captures: Captures = vec![None; self.captures_count];
+        let matched =
+            matcher.match_sequence(nodes, 0, &mut captures, &mut |end, _| end == input.len());
+        if !matched {
+            return None;
+        }
+        Some(
+            captures
+                .into_iter()
+                .map(|range| range.map_or(vec![], |(start, end)| input[start..end].to_vec()))
+                .collect(),
+        )
+    }
+}
+
+/// Replaces text of literal nodes with its case-folded form
+fn fold_literals(nodes: &mut [Node]) {
+    for node in nodes {
+        match node {
+            Node::Literal(text) => *text = text.chars().flat_map(fold_case).collect(),
+            Node::Alternation { alternatives, .. } => {
+                for alternative in alternatives {
+                    fold_literals(alternative);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns `true` if text contains characters that are special in glob patterns
+///
+/// # Examples
+/// ```
+/// use mmv::filename_operations::pattern::has_wildcards;
+/// assert!(has_wildcards("logs_*"));
+/// assert!(has_wildcards("\\*"));
+/// assert!(!has_wildcards("logs"));
+/// ```
+pub fn has_wildcards(text: &str) -> bool {
+    text.contains(['*', '?', '[', '{', '\\'])
+}
+
+/// Decodes the character starting at `position` and returns it with its length in bytes
+///
+/// Invalid UTF-8 sequences are treated as single-byte characters and decoded as [`None`]
+fn decode_char(input: &[u8], position: usize) -> (Option<char>, usize) {
+    let expected = match input[position] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let end = (position + expected).min(input.len());
+    match std::str::from_utf8(&input[position..end]) {
+        Ok(text) => (text.chars().next(), expected),
+        Err(_) => (None, 1),
+    }
+}
+
+/// Backtracking matcher over a single input
+///
+/// Whether a sequence of nodes can be matched depends only on the sequence and
+/// the position, so failed attempts are remembered and every pair is tried at most once
+struct Matcher<'a> {
+    input: &'a [u8],
+    ignore_case: bool,
+    /// Address and length of node sequences with positions where they failed
+    failed: RefCell<HashSet<(usize, usize, usize)>>,
+    /// Number of nodes tried so far
+    steps: Cell<usize>,
+}
+
+impl Matcher<'_> {
+    /// Positions of character boundaries from `start` to the end of input inclusive
+    fn boundaries(&self, start: usize) -> Vec<usize> {
+        let mut positions = vec![start];
+        let mut position = start;
+        while position < self.input.len() {
+            position += decode_char(self.input, position).1;
+            positions.push(position);
+        }
+        positions
+    }
+
+    /// Returns end position of `text` if input at `position` starts with it
+    ///
+    /// Without `ignore_case` bytes are compared exactly. Otherwise `text` must be already
+    /// case-folded and input characters are folded one by one
+    fn match_literal(&self, text: &str, position: usize) -> Option<usize> {
+        if !self.ignore_case {
+            return self.input[position..]
+                .starts_with(text.as_bytes())
+                .then_some(position + text.len());
+        }
+        let mut folded = String::new();
+        let mut end = position;
+        while folded.len() < text.len() {
+            if end == self.input.len() {
+                return None;
+            }
+            let (symbol, length) = decode_char(self.input, end);
+            folded.extend(fold_case(symbol?));
+            if !text.starts_with(&folded) {
+                return None;
+            }
+            end += length;
+        }
+        Some(end)
+    }
+
+    /// Matches `nodes` starting at `position` and passes the end position to `next`
+    ///
+    /// Returns `true` as soon as `next` accepts, trying alternatives otherwise
+    fn match_sequence(
+        &self,
+        nodes: &[Node],
+        position: usize,
+        captures: &mut Captures,
+        next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+    ) -> bool {
+        let Some((node, rest)) = nodes.split_first() else {
+            return next(position, captures);
+        };
+        let key = (nodes.as_ptr() as usize, nodes.len(), position);
+        if self.failed.borrow().contains(&key) {
+            return false;
+        }
+        let matched = self.match_node(node, rest, position, captures, next);
+        if !matched {
+            self.failed.borrow_mut().insert(key);
+        }
+        matched
+    }
+
+    /// Matches `node` followed by `rest` starting at `position`, see [`Self::match_sequence`]
+    fn match_node(
+        &self,
+        node: &Node,
+        rest: &[Node],
+        position: usize,
+        captures: &mut Captures,
+        next: &mut dyn FnMut(usize, &mut Captures) -> bool,
+    ) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        match node {
+            Node::Literal(text) => match self.match_literal(text, position) {
+                Some(end) => self.match_sequence(rest, end, captures, next),
+                None => false,
+            },
+            Node::Star { greed, index } => {
+                let mut ends = self.boundaries(position);
+                if *greed == Greed::Greedy {
+                    ends.reverse();
+                }
+                for end in ends {
+                    captures[*index] = Some((position, end));
+                    if self.match_sequence(rest, end, captures, next) {
+                        return true;
+                    }
+                }
+                captures[*index] = None;
+                false
+            }
+            Node::AnyChar { index } | Node::Class { index, .. } => {
+                if position == self.input.len() {
+                    return false;
+                }
+                let (symbol, length) = decode_char(self.input, position);
+                if let Node::Class { class, .. } = node {
+                    if !class.contains(symbol, self.ignore_case) {
+                        return false;
+                    }
+                }
+                captures[*index] = Some((position, position + length));
+                if self.match_sequence(rest, position + length, captures, next) {
+                    return true;
+                }
+                captures[*index] = None;
+                false
+            }
+            Node::Alternation {
+                alternatives,
+                index,
+            } => {
+                for alternative in alternatives {
+                    let matched = self.match_sequence(
+                        alternative,
+                        position,
+                        captures,
+                        &mut |end, captures| {
+                            captures[*index] = Some((position, end));
+                            self.match_sequence(rest, end, captures, next)
+                        },
+                    );
+                    if matched {
+                        return true;
+                    }
+                }
+                captures[*index] = None;
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Engine, Matcher, Pattern, PatternOptions};
+    use std::cell::{Cell, RefCell};
+
+    fn fragments(pattern: &str, input: &str) -> Option<Vec<String>> {
+        Pattern::parse(pattern)
+            .unwrap()
+            .match_bytes(input.as_bytes())
+            .map(|fragments| {
+                fragments
+                    .into_iter()
+                    .map(|fragment| String::from_utf8(fragment).unwrap())
+                    .collect()
+            })
+    }
+
+    #[test]
+    fn backtracking() {
+        assert_eq!(fragments("*.txt", "a.txt.txt").unwrap(), vec!["a.txt"]);
+        assert_eq!(fragments("*_b", "a_b_b").unwrap(), vec!["a_b"]);
+        assert_eq!(fragments("*_b*", "a_c_b").unwrap(), vec!["a_c", ""]);
+        assert_eq!(fragments("*.*.gz", "a.b.c.gz").unwrap(), vec!["a", "b.c"]);
+        assert!(fragments("*.txt", "a.txt.bin").is_none());
+    }
+
+    /// Returns whether `input` matches and how many nodes were tried
+    fn count_steps(pattern: &str, input: &str) -> (bool, usize) {
+        let pattern = Pattern::parse(pattern).unwrap();
+        let Engine::Glob(nodes) = &pattern.engine else {
+            unreachable!()
+        };
+        let matcher = Matcher {
+            input: input.as_bytes(),
+            ignore_case: false,
+            failed: RefCell::default(),
+            steps: Cell::default(),
+        };
+        let matched = matcher.match_sequence(
+            nodes,
+            0,
+            &mut vec![None; pattern.captures_count()],
+            &mut |end, _| end == input.len(),
+        );
+        (matched, matcher.steps.get())
+    }
+
+    #[test]
+    fn many_stars_on_long_name() {
+        let name = "a".repeat(200);
+        let (matched, steps) = count_steps("*a*a*a*a*a*a*b", &name);
+        assert!(!matched);
+        assert!(steps <= 14 * (name.len() + 1));
+        let (matched, steps) = count_steps("{*a,*b}{*a,*b}{*a,*b}{*a,*b}c", &name);
+        assert!(!matched);
+        assert!(steps <= 4 * 5 * (name.len() + 1));
+        assert_eq!(
+            fragments("*a*a*a*a*a*a*", &name).unwrap(),
+            vec!["", "", "", "", "", "", &name[6..]]
+        );
+    }
+
+    #[test]
+    fn greed() {
+        assert_eq!(fragments("*.*", "a.b.c").unwrap(), vec!["a", "b.c"]);
+        assert_eq!(fragments("*+.*", "a.b.c").unwrap(), vec!["a.b", "c"]);
+        assert_eq!(fragments("*+.*+", "a.b.c").unwrap(), vec!["a.b", "c"]);
+        assert_eq!(fragments("*+", "😀😃").unwrap(), vec!["😀😃"]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(fragments("\\*_*", "*_a").unwrap(), vec!["a"]);
+        assert_eq!(fragments("*\\+", "a+").unwrap(), vec!["a"]);
+        assert!(fragments("\\*", "a").is_none());
+        assert!(Pattern::parse("abc\\").is_err());
+        assert_eq!(fragments("\\?\\[*]", "?[a]").unwrap(), vec!["a"]);
+    }
+
+    #[test]
+    fn single_characters() {
+        assert_eq!(
+            fragments("scan_????_?.tif", "scan_0042_a.tif").unwrap(),
+            vec!["0", "0", "4", "2", "a"]
+        );
+        assert_eq!(fragments("?*", "😀😃").unwrap(), vec!["😀", "😃"]);
+        assert!(fragments("?", "").is_none());
+        assert!(fragments("??", "a").is_none());
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(fragments("[abc]*", "b1").unwrap(), vec!["b", "1"]);
+        assert_eq!(fragments("[a-z][0-9]", "x7").unwrap(), vec!["x", "7"]);
+        assert_eq!(fragments("[!0-9]*", "x7").unwrap(), vec!["x", "7"]);
+        assert_eq!(fragments("[]-]*", "]a").unwrap(), vec!["]", "a"]);
+        assert_eq!(fragments("[a-]*", "-a").unwrap(), vec!["-", "a"]);
+        assert_eq!(fragments("[\\!]", "!").unwrap(), vec!["!"]);
+        assert_eq!(fragments("[а-я]", "ж").unwrap(), vec!["ж"]);
+        assert!(fragments("[!0-9]", "5").is_none());
+        assert!(fragments("[abc]", "d").is_none());
+        assert!(Pattern::parse("[abc").is_err());
+        assert!(Pattern::parse("[z-a]").is_err());
+    }
+
+    #[test]
+    fn alternations() {
+        assert_eq!(
+            fragments("*.{jpg,jpeg,png}", "a.jpeg").unwrap(),
+            vec!["a", "jpeg"]
+        );
+        assert_eq!(fragments("{a,ab}c", "abc").unwrap(), vec!["ab"]);
+        assert_eq!(fragments("{,x}*", "xy").unwrap(), vec!["", "xy"]);
+        assert_eq!(
+            fragments("{img_{a,b},raw*}.tif", "img_b.tif").unwrap(),
+            vec!["img_b", "b", ""]
+        );
+        assert_eq!(
+            fragments("{img_{a,b},raw*}.tif", "raw12.tif").unwrap(),
+            vec!["raw12", "", "12"]
+        );
+        assert_eq!(fragments("a,b}", "a,b}").unwrap(), Vec::<String>::new());
+        assert_eq!(fragments("{a\\,b,c}", "a,b").unwrap(), vec!["a,b"]);
+        assert!(fragments("*.{jpg,png}", "a.gif").is_none());
+        assert!(Pattern::parse("{a,b").is_err());
+        assert!(Pattern::parse("{a,{b}").is_err());
+    }
+
+    #[test]
+    fn ignore_case() {
+        let options = PatternOptions {
+            ignore_case: true,
+            ..Default::default()
+        };
+        let fragments = |pattern: &str, input: &str| {
+            Pattern::parse_with(pattern, &options)
+                .unwrap()
+                .match_bytes(input.as_bytes())
+                .map(|fragments| {
+                    fragments
+                        .into_iter()
+                        .map(|fragment| String::from_utf8(fragment).unwrap())
+                        .collect::<Vec<_>>()
+                })
+        };
+        assert_eq!(fragments("*.jpg", "Photo.JPG").unwrap(), vec!["Photo"]);
+        assert_eq!(fragments("*.jpg", "Photo.Jpg").unwrap(), vec!["Photo"]);
+        assert_eq!(fragments("ПРИВЕТ_*", "привет_Мир").unwrap(), vec!["Мир"]);
+        assert_eq!(fragments("straße_*", "STRASSE_1").unwrap(), vec!["1"]);
+        assert_eq!(fragments("σ*", "ςx").unwrap(), vec!["x"]);
+        assert_eq!(fragments("[a-c]*", "Bx").unwrap(), vec!["B", "x"]);
+        assert_eq!(fragments("*.{jpg,png}", "a.PNG").unwrap(), vec!["a", "PNG"]);
+        assert!(fragments("*.jpg", "a.jpeg").is_none());
+        assert!(Pattern::parse("*.jpg")
+            .unwrap()
+            .match_bytes(b"a.JPG")
+            .is_none());
+        let regex = PatternOptions {
+            regex: true,
+            ignore_case: true,
+        };
+        let pattern = Pattern::parse_with(r"(\w+)\.jpe?g", &regex).unwrap();
+        assert_eq!(
+            pattern.match_bytes(b"Cat.JPEG").unwrap(),
+            vec![b"Cat".to_vec()]
+        );
+    }
+
+    #[test]
+    fn regular_expressions() {
+        let pattern = Pattern::parse_regex(r"(\w+?)(_(?<number>\d+))?\.(jpe?g)").unwrap();
+        assert_eq!(pattern.captures_count(), 4);
+        assert_eq!(
+            pattern.capture_names(),
+            [(String::from("number"), 2)].into_iter().collect()
+        );
+        assert_eq!(
+            pattern.match_bytes(b"img_12.jpeg").unwrap(),
+            vec![
+                b"img".to_vec(),
+                b"_12".to_vec(),
+                b"12".to_vec(),
+                b"jpeg".to_vec()
+            ]
+        );
+        assert_eq!(
+            pattern.match_bytes(b"img.jpg").unwrap(),
+            vec![b"img".to_vec(), vec![], vec![], b"jpg".to_vec()]
+        );
+        assert!(pattern.match_bytes(b"img.jpg.bak").is_none());
+        let lazy = Pattern::parse_regex(r"(\w+?)").unwrap();
+        assert_eq!(lazy.match_bytes(b"abc").unwrap(), vec![b"abc".to_vec()]);
+        let commented = Pattern::parse_regex("(?x) (\\d+) \\.txt # number and extension").unwrap();
+        assert_eq!(
+            commented.match_bytes(b"12.txt").unwrap(),
+            vec![b"12".to_vec()]
+        );
+        assert!(commented.match_bytes(b"12.txt.bak").is_none());
+        assert!(Pattern::parse_regex("a|b")
+            .unwrap()
+            .match_bytes(b"ab")
+            .is_none());
+        let error = Pattern::parse_regex("img_(\\d+").unwrap_err().to_string();
+        assert!(error.contains("img_(\\d+"));
+        assert!(error.contains("unclosed group"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Backups of targets that are about to be overwritten, named like GNU `mv` does
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Suffix of simple backups if no other is given
+pub const DEFAULT_BACKUP_SUFFIX: &str = "~";
+
+/// How backups are named
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupMode {
+    /// `file` is kept as `file~`
+    Simple,
+    /// `file` is kept as `file.~1~`, `file.~2~` and so on
+    Numbered,
+    /// Numbered if numbered backups of the file already exist, simple otherwise
+    #[default]
+    Existing,
+}
+
+/// Backups of overwritten targets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    pub mode: BackupMode,
+    /// Suffix of simple backups
+    pub suffix: String,
+}
+
+/// Returns the highest number of numbered backups of `path`, 0 if there are none
+fn last_number(path: &Path) -> u64 {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return 0;
+    };
+    let prefix = format!("{}.~", name.to_string_lossy());
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    fs::read_dir(parent)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .ok()?
+                .file_name()
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .strip_suffix('~')?
+                .parse()
+                .ok()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+impl Backup {
+    /// Returns path the backup of `path` is kept at
+    pub fn path(&self, path: &Path) -> PathBuf {
+        let last = last_number(path);
+        let numbered = match self.mode {
+            BackupMode::Simple => false,
+            BackupMode::Numbered => true,
+            BackupMode::Existing => last > 0,
+        };
+        let mut name = path.as_os_str().to_os_string();
+        if numbered {
+            name.push(format!(".~{}~", last + 1));
+        } else {
+            name.push(&self.suffix);
+        }
+        PathBuf::from(name)
+    }
+
+    /// Renames `path` to its backup if it exists, returns the path of the backup
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors of [`fs::rename`]
+    pub fn keep(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        if fs::symlink_metadata(path).is_err() {
+            return Ok(None);
+        }
+        let backup = self.path(path);
+        fs::rename(path, &backup)?;
+        Ok(Some(backup))
+    }
+}
+
+/// Moves the backup back to `path`
+///
+/// # Errors
+///
+/// Returns [`io::ErrorKind::AlreadyExists`] if `path` is taken, propagates errors
+/// of [`fs::rename`]
+pub fn restore(backup: &Path, path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists", path.display()),
+        ));
+    }
+    fs::rename(backup, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{restore, Backup, BackupMode};
+    use crate::files::tests::FileManager;
+    use std::fs;
+
+    #[test]
+    fn backup_names() {
+        let manager = FileManager::from("backup_names");
+        manager.create_files(&vec!["a", "b"]);
+        let root = manager.get_directory();
+        let backup = |mode: BackupMode| Backup {
+            mode,
+            suffix: String::from("~"),
+        };
+        assert_eq!(
+            backup(BackupMode::Existing).path(&root.join("a")),
+            root.join("a~")
+        );
+        let kept = backup(BackupMode::Numbered).keep(&root.join("a")).unwrap();
+        assert_eq!(kept, Some(root.join("a.~1~")));
+        fs::write(root.join("a"), "new").unwrap();
+        let kept = backup(BackupMode::Existing).keep(&root.join("a")).unwrap();
+        assert_eq!(kept, Some(root.join("a.~2~")));
+        assert_eq!(
+            backup(BackupMode::Simple).keep(&root.join("a")).unwrap(),
+            None
+        );
+        assert!(restore(&root.join("a.~1~"), &root.join("b")).is_err());
+        restore(&root.join("a.~1~"), &root.join("a")).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("a")).unwrap(),
+            "Original name: a"
+        );
+    }
+}
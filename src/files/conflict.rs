@@ -0,0 +1,380 @@
+//! Resolving targets that already exist
+use super::plan::normalize;
+use super::prompt::{Confirmation, Prompt};
+use super::r#move::{is_taken, sources, MoveOptions};
+use crate::errors::CommonError as Error;
+use crate::filename_operations::modified::{ModifiedFilename, ModifiedFilenames};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Suffix of renamed targets if [`MoveOptions::rename_suffix`] is not set
+pub const DEFAULT_RENAME_SUFFIX: &str = "_#";
+
+/// What is done with a file whose target already exists
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// The run fails with [`Error::FilenameAlreadyExists`]
+    #[default]
+    #[value(skip)]
+    Fail,
+    /// The file is left in place
+    Skip,
+    /// The target is overwritten
+    Overwrite,
+    /// The file gets a free name with a numbered suffix
+    Rename,
+    /// The target is overwritten if the file was modified later, otherwise the file is skipped
+    Newer,
+    /// The target is overwritten if the file is larger, otherwise the file is skipped
+    Larger,
+    /// The user is asked whether to overwrite the target
+    Ask,
+}
+
+/// What happened to a file whose target already existed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    Skipped,
+    Overwritten,
+    Renamed(PathBuf),
+    /// The user would be asked with [`OnConflict::Ask`], only in dry-run mode
+    Undecided,
+}
+
+/// File whose target already existed and what happened to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub resolution: Resolution,
+    /// The conflict is only planned, nothing was done in dry-run mode
+    pub planned: bool,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (source, target) = (self.source.display(), self.target.display());
+        let verb = |done: &'static str, planned: &'static str| {
+            if self.planned {
+                planned
+            } else {
+                done
+            }
+        };
+        match &self.resolution {
+            Resolution::Skipped => write!(
+                f,
+                "{} {}: {} exists",
+                verb("skipped", "would skip"),
+                source,
+                target
+            ),
+            Resolution::Overwritten => write!(
+                f,
+                "{} {} with {}",
+                verb("overwritten", "would overwrite"),
+                target,
+                source
+            ),
+            Resolution::Renamed(renamed) => write!(
+                f,
+                "{} {} to {}: {} exists",
+                verb("renamed", "would rename"),
+                source,
+                renamed.display(),
+                target
+            ),
+            Resolution::Undecided => {
+                write!(f, "would ask to overwrite {} with {}", target, source)
+            }
+        }
+    }
+}
+
+/// Returns `name` with the numbered suffix inserted before the extension
+///
+/// `#` in the suffix is replaced with the number, the number is appended
+/// if the suffix has no `#`
+///
+/// # Examples
+/// ```
+/// use std::ffi::OsStr;
+/// use mmv::files::conflict::numbered_name;
+/// assert_eq!(numbered_name(OsStr::new("photo.jpg"), "_#", 2), "photo_2.jpg");
+/// assert_eq!(numbered_name(OsStr::new("notes"), " (copy #)", 1), "notes (copy 1)");
+/// assert_eq!(numbered_name(OsStr::new(".bashrc"), "-", 3), ".bashrc-3");
+/// ```
+pub fn numbered_name(name: &OsStr, suffix: &str, number: usize) -> OsString {
+    let path = Path::new(name);
+    let suffix = if suffix.contains('#') {
+        suffix.replace('#', &number.to_string())
+    } else {
+        format!("{}{}", suffix, number)
+    };
+    let mut numbered = path.file_stem().unwrap_or(name).to_os_string();
+    numbered.push(suffix);
+    if let Some(extension) = path.extension() {
+        numbered.push(".");
+        numbered.push(extension);
+    }
+    numbered
+}
+
+/// Returns the first name with a numbered suffix that is neither on disk nor in `reserved`
+fn free_name(
+    changed: &Path,
+    directory_out: &Path,
+    suffix: &str,
+    reserved: &HashSet<PathBuf>,
+) -> PathBuf {
+    let name = changed.file_name().unwrap_or(changed.as_os_str());
+    (1..)
+        .map(|number| changed.with_file_name(numbered_name(name, suffix, number)))
+        .find(|candidate| {
+            let target = directory_out.join(candidate);
+            target.symlink_metadata().is_err() && !reserved.contains(&normalize(&target))
+        })
+        .unwrap()
+}
+
+/// Decides whether the target is overwritten by `newer` and `larger` strategies
+fn should_overwrite(on_conflict: OnConflict, source: &Path, target: &Path) -> io::Result<bool> {
+    let (source_metadata, target_metadata) = (fs::metadata(source)?, fs::metadata(target)?);
+    Ok(match on_conflict {
+        OnConflict::Newer => source_metadata.modified()? > target_metadata.modified()?,
+        OnConflict::Larger => source_metadata.len() > target_metadata.len(),
+        _ => on_conflict == OnConflict::Overwrite,
+    })
+}
+
+/// Applies [`MoveOptions::on_conflict`] to every file whose target already exists
+///
+/// Returns files that are still moved, with renamed targets, and every conflict
+/// with its resolution. Files whose target is their own path already have their names,
+/// they are left out instead of being reported as conflicts.
+/// Nothing is resolved with [`OnConflict::Fail`], such targets
+/// are reported by the checks of [`move_files`](super::r#move::move_files).
+/// [`OnConflict::Ask`] asks the prompt whether to overwrite each target.
+/// A skipped file keeps its path, so files moved to it are resolved as conflicts too.
+/// In dry-run mode nothing is asked, such files stay in the plan as [`Resolution::Undecided`]
+///
+/// # Errors
+///
+/// Returns [`Error::IOError`] if files could not be compared or the answer could not be read
+///
+/// Returns [`Error::Aborted`] if the user quits
+pub fn resolve_conflicts(
+    modified: ModifiedFilenames,
+    directory_in: &Path,
+    directory_out: &Path,
+    options: &MoveOptions,
+    prompt: &mut dyn Prompt,
+) -> Result<(ModifiedFilenames, Vec<Conflict>), Error> {
+    if options.on_conflict == OnConflict::Fail {
+        return Ok((modified, vec![]));
+    }
+    let modified: ModifiedFilenames = modified
+        .into_iter()
+        .filter(|filename| {
+            normalize(&directory_in.join(&filename.origin))
+                != normalize(&directory_out.join(&filename.changed))
+        })
+        .collect();
+    let mut sources = sources(&modified, directory_in);
+    let mut reserved: HashSet<PathBuf> = modified
+        .iter()
+        .map(|filename| normalize(&directory_out.join(&filename.changed)))
+        .collect();
+    let mut confirmation = Confirmation::new(prompt);
+    let mut free: Vec<(usize, ModifiedFilename)> = modified.into_iter().enumerate().collect();
+    let mut resolved = vec![];
+    let mut conflicts = vec![];
+    // A skipped file stays in place, so its path is taken for moves to it.
+    // Such moves were taken as free before and are checked again until nothing is skipped
+    loop {
+        let taken: Vec<(usize, ModifiedFilename)>;
+        (taken, free) = free.into_iter().partition(|(_, filename)| {
+            is_taken(
+                filename,
+                &sources,
+                directory_in,
+                directory_out,
+                options.operation,
+            )
+        });
+        if taken.is_empty() {
+            break;
+        }
+        for (index, filename) in taken {
+            let source = directory_in.join(&filename.origin);
+            let target = directory_out.join(&filename.changed);
+            let resolution = if options.on_conflict == OnConflict::Rename {
+                let renamed = free_name(
+                    Path::new(&filename.changed),
+                    directory_out,
+                    options
+                        .rename_suffix
+                        .as_deref()
+                        .unwrap_or(DEFAULT_RENAME_SUFFIX),
+                    &reserved,
+                );
+                reserved.insert(normalize(&directory_out.join(&renamed)));
+                resolved.push((
+                    index,
+                    ModifiedFilename {
+                        origin: filename.origin,
+                        changed: renamed.clone().into_os_string(),
+                    },
+                ));
+                Resolution::Renamed(directory_out.join(renamed))
+            } else if options.on_conflict == OnConflict::Ask && options.dry_run {
+                resolved.push((index, filename));
+                Resolution::Undecided
+            } else if options.on_conflict == OnConflict::Ask {
+                let question = format!("overwrite {} with {}?", target.display(), source.display());
+                if confirmation.confirm(&question)? {
+                    resolved.push((index, filename));
+                    Resolution::Overwritten
+                } else {
+                    Resolution::Skipped
+                }
+            } else if options.on_conflict != OnConflict::Skip
+                && should_overwrite(options.on_conflict, &source, &target)
+                    .map_err(|error| Error::IOError { error })?
+            {
+                resolved.push((index, filename));
+                Resolution::Overwritten
+            } else {
+                Resolution::Skipped
+            };
+            if resolution == Resolution::Skipped {
+                sources.remove(&normalize(&source));
+            }
+            conflicts.push(Conflict {
+                source,
+                target,
+                resolution,
+                planned: options.dry_run,
+            });
+        }
+    }
+    resolved.extend(free);
+    resolved.sort_by_key(|(index, _)| *index);
+    let resolved = resolved.into_iter().map(|(_, filename)| filename).collect();
+    Ok((resolved, conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_conflicts, OnConflict, Resolution};
+    use crate::files::prompt::Answer;
+    use crate::files::r#move::MoveOptions;
+    use crate::files::tests::{modified, FileManager};
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn strategies() {
+        let manager = FileManager::from("conflict_strategies");
+        manager.create_files(&vec!["a.txt", "b.txt", "a_1.txt"]);
+        let root = manager.get_directory();
+        let old = SystemTime::now() - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(root.join("b.txt"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let resolve = |on_conflict: OnConflict, answers: Vec<Answer>| {
+            let options = MoveOptions {
+                on_conflict,
+                ..Default::default()
+            };
+            let modified = vec![modified("b.txt", "a.txt")];
+            resolve_conflicts(modified, root, root, &options, &mut answers.clone()).unwrap()
+        };
+
+        let (resolved, conflicts) = resolve(OnConflict::Skip, vec![]);
+        assert!(resolved.is_empty());
+        assert_eq!(conflicts[0].resolution, Resolution::Skipped);
+        let (resolved, conflicts) = resolve(OnConflict::Overwrite, vec![]);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(conflicts[0].resolution, Resolution::Overwritten);
+        let (resolved, _) = resolve(OnConflict::Rename, vec![]);
+        assert_eq!(resolved[0].changed, "a_2.txt");
+        assert!(resolve(OnConflict::Newer, vec![]).0.is_empty());
+        assert!(resolve(OnConflict::Larger, vec![]).0.is_empty());
+        assert!(resolve(OnConflict::Ask, vec![Answer::No]).0.is_empty());
+        assert_eq!(resolve(OnConflict::Ask, vec![Answer::Yes]).0.len(), 1);
+        fs::write(root.join("b.txt"), "Original name: b.txt, longer").unwrap();
+        assert_eq!(resolve(OnConflict::Newer, vec![]).0.len(), 1);
+        assert_eq!(resolve(OnConflict::Larger, vec![]).0.len(), 1);
+        assert!(resolve(OnConflict::Fail, vec![]).1.is_empty());
+    }
+
+    #[test]
+    fn skipped_chain() {
+        let manager = FileManager::from("conflict_skipped_chain");
+        manager.create_files(&vec!["a", "b", "c"]);
+        let root = manager.get_directory();
+        let resolve = |on_conflict: OnConflict, answers: Vec<Answer>| {
+            let options = MoveOptions {
+                on_conflict,
+                ..Default::default()
+            };
+            let modified = vec![modified("a", "b"), modified("b", "c")];
+            resolve_conflicts(modified, root, root, &options, &mut answers.clone()).unwrap()
+        };
+        for (on_conflict, answers) in [
+            (OnConflict::Skip, vec![]),
+            (OnConflict::Ask, vec![Answer::No, Answer::No]),
+            (OnConflict::Newer, vec![]),
+            (OnConflict::Larger, vec![]),
+        ] {
+            let (resolved, conflicts) = resolve(on_conflict, answers);
+            assert!(resolved.is_empty(), "{:?}", on_conflict);
+            assert_eq!(conflicts.len(), 2);
+            assert!(conflicts
+                .iter()
+                .all(|conflict| conflict.resolution == Resolution::Skipped));
+            assert_eq!(conflicts[1].target, root.join("b"));
+        }
+        let (resolved, _) = resolve(OnConflict::Ask, vec![Answer::Yes]);
+        assert_eq!(resolved.len(), 2);
+    }
+
+    #[test]
+    fn dry_run() {
+        let manager = FileManager::from("conflict_dry_run");
+        manager.create_files(&vec!["a.txt", "b.txt"]);
+        let root = manager.get_directory();
+        let resolve = |on_conflict: OnConflict| {
+            let options = MoveOptions {
+                on_conflict,
+                dry_run: true,
+                ..Default::default()
+            };
+            let modified = vec![modified("b.txt", "a.txt")];
+            resolve_conflicts(modified, root, root, &options, &mut vec![]).unwrap()
+        };
+        let (resolved, conflicts) = resolve(OnConflict::Ask);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(conflicts[0].resolution, Resolution::Undecided);
+        assert!(conflicts[0]
+            .to_string()
+            .starts_with("would ask to overwrite"));
+        let (_, conflicts) = resolve(OnConflict::Overwrite);
+        assert!(conflicts[0].to_string().starts_with("would overwrite"));
+        let (_, conflicts) = resolve(OnConflict::Rename);
+        assert!(conflicts[0].to_string().starts_with("would rename"));
+        let (_, conflicts) = resolve(OnConflict::Skip);
+        assert!(conflicts[0].to_string().starts_with("would skip"));
+    }
+}
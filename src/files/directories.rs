@@ -0,0 +1,93 @@
+//! Utils to create missing target directories and remove them again
+use crate::errors::CommonError as Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories created during a run, in order of creation
+#[derive(Debug, Default)]
+pub struct CreatedDirectories {
+    directories: Vec<PathBuf>,
+}
+
+impl CreatedDirectories {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates missing ancestors of `path` and remembers every created directory
+    ///
+    /// Returns directories created by this call in order of creation
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if a directory could not be created
+    pub fn create_parent(&mut self, path: &Path) -> Result<&[PathBuf], Error> {
+        let created = self.directories.len();
+        let Some(parent) = path.parent() else {
+            return Ok(&[]);
+        };
+        let mut missing = vec![];
+        let mut ancestor = parent;
+        while !ancestor.as_os_str().is_empty() && !ancestor.exists() {
+            missing.push(ancestor.to_path_buf());
+            match ancestor.parent() {
+                Some(next) => ancestor = next,
+                None => break,
+            }
+        }
+        for directory in missing.into_iter().rev() {
+            fs::create_dir(&directory).map_err(|error| Error::IOError { error })?;
+            self.directories.push(directory);
+        }
+        Ok(&self.directories[created..])
+    }
+
+    /// Returns created directories in order of creation
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+
+    /// Removes created directories in reverse order
+    ///
+    /// Returns `true` if every directory was removed.
+    /// Directories that are not empty are kept
+    pub fn remove(self) -> bool {
+        let mut removed = true;
+        for directory in self.directories.into_iter().rev() {
+            removed &= fs::remove_dir(directory).is_ok();
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CreatedDirectories;
+    use crate::files::tests::FileManager;
+    use std::fs;
+
+    #[test]
+    fn create_and_remove() {
+        let manager = FileManager::from("created_directories");
+        let root = manager.get_directory();
+        let mut created = CreatedDirectories::new();
+        created.create_parent(&root.join("a/b/file")).unwrap();
+        assert_eq!(
+            created.create_parent(&root.join("a/c/file")).unwrap(),
+            [root.join("a/c")]
+        );
+        assert!(created
+            .create_parent(&root.join("file"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            created.directories(),
+            [root.join("a"), root.join("a/b"), root.join("a/c")]
+        );
+        assert!(root.join("a/b").is_dir());
+        fs::write(root.join("a/c/file"), "file").unwrap();
+        assert!(!created.remove());
+        assert!(!root.join("a/b").exists());
+        assert!(root.join("a/c/file").exists());
+    }
+}
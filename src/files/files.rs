@@ -1,8 +1,11 @@
 //! Utils to work with files
 use crate::errors::CommonError as Error;
+use crate::filename_operations::matched::{match_filename_with, MatchedFilename, MatchedFilenames};
+use crate::filename_operations::pattern::{has_wildcards, Pattern, PatternOptions};
+use std::collections::HashSet;
 use std::ffi::OsString;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// Returns parent directory of path
 ///
@@ -19,7 +22,7 @@ pub fn get_directory(path: &Path) -> Result<PathBuf, Error> {
                 Ok(directory.to_path_buf())
             } else {
                 Err(Error::InvalidDirectoryPath {
-                    path: directory.to_str().unwrap().to_string(),
+                    path: directory.display().to_string(),
                 })
             }
         }
@@ -32,6 +35,8 @@ pub fn get_directory(path: &Path) -> Result<PathBuf, Error> {
 ///
 /// Returns [`Error::InvalidNamePattern`] if path does not have file_name
 ///
+/// Returns [`Error::NonUnicodeName`] if file_name is not valid UTF-8
+///
 /// # Examples
 /// ```
 /// use std::path::{Path, PathBuf};
@@ -55,7 +60,12 @@ pub fn get_directory(path: &Path) -> Result<PathBuf, Error> {
 pub fn get_filename(path: &Path) -> Result<String, Error> {
     match path.file_name() {
         None => Err(Error::InvalidNamePattern),
-        Some(filename) => Ok(String::from(filename.to_str().unwrap())),
+        Some(filename) => match filename.to_str() {
+            Some(filename) => Ok(String::from(filename)),
+            None => Err(Error::NonUnicodeName {
+                name: filename.to_string_lossy().into_owned(),
+            }),
+        },
     }
 }
 
@@ -66,13 +76,38 @@ pub fn get_filename(path: &Path) -> Result<String, Error> {
 /// # Errors
 ///
 /// Returns [`Error::IOError`] if could not open directory with [`fs::read_dir`]
+/// or read one of its entries
 pub fn get_filenames(path: &Path) -> Result<Vec<OsString>, Error> {
     let read_dir = fs::read_dir(path).map_err(|error| Error::IOError { error })?;
-    Ok(read_dir
-        .map(|entry_res| entry_res.unwrap())
-        .filter(|file| file.metadata().unwrap().is_file())
-        .map(|file| file.file_name())
-        .collect())
+    let mut filenames = vec![];
+    for entry in read_dir {
+        let entry = entry.map_err(|error| Error::IOError { error })?;
+        let metadata = entry.metadata().map_err(|error| Error::IOError { error })?;
+        if metadata.is_file() {
+            filenames.push(entry.file_name());
+        }
+    }
+    Ok(filenames)
+}
+
+/// Gets names of subdirectories of the given directory
+///
+/// Symbolic links to directories are included
+///
+/// # Errors
+///
+/// Returns [`Error::IOError`] if could not open directory with [`fs::read_dir`]
+/// or read one of its entries
+pub fn get_subdirectories(path: &Path) -> Result<Vec<OsString>, Error> {
+    let read_dir = fs::read_dir(path).map_err(|error| Error::IOError { error })?;
+    let mut subdirectories = vec![];
+    for entry in read_dir {
+        let entry = entry.map_err(|error| Error::IOError { error })?;
+        if entry.path().is_dir() {
+            subdirectories.push(entry.file_name());
+        }
+    }
+    Ok(subdirectories)
 }
 
 #[inline]
@@ -82,10 +117,299 @@ pub fn split_path_to_filename(path: &str) -> Result<(PathBuf, String), Error> {
     Ok((get_directory(path)?, get_filename(path)?))
 }
 
+/// Directory component of the input template that follows the base directory
+#[derive(Debug, Clone)]
+pub enum DirectoryPattern {
+    /// `**`, matches any number of nested directories including none
+    Recursive,
+    /// Directory whose name matches the glob pattern
+    Name(Pattern),
+}
+
+/// Splits input template into base directory, directory patterns and filename pattern
+///
+/// Base directory is the longest prefix of the template without wildcards.
+/// Every following directory component is a glob [`Pattern`] or `**`,
+/// parsed with `options` except [`PatternOptions::regex`].
+///
+/// With [`PatternOptions::regex`] the filename pattern starts after the last `/`
+/// that is outside of groups and classes of the expression, see [`split_regex`]
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidDirectoryPath`] if base directory was not found in system
+///
+/// Returns [`Error::InvalidNamePattern`] if template does not end with a filename pattern,
+/// has `.` or `..` after a wildcard or a directory pattern cannot be parsed
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+/// use mmv::files::files::{split_input_template, DirectoryPattern};
+/// let (base, directories, pattern) =
+///     split_input_template("src/**/*.rs", &Default::default()).unwrap();
+/// assert_eq!(base, PathBuf::from("src"));
+/// assert!(matches!(directories[..], [DirectoryPattern::Recursive]));
+/// assert_eq!(pattern, "*.rs");
+/// ```
+pub fn split_input_template(
+    template: &str,
+    options: &PatternOptions,
+) -> Result<(PathBuf, Vec<DirectoryPattern>, String), Error> {
+    let directory_options = PatternOptions {
+        regex: false,
+        ..*options
+    };
+    let (parent, pattern) = if options.regex {
+        let (parent, pattern) = split_regex(template);
+        if pattern.is_empty() {
+            return Err(Error::InvalidNamePattern);
+        }
+        (PathBuf::from(parent), pattern.to_string())
+    } else {
+        let path = PathBuf::from(template);
+        let pattern = get_filename(&path)?;
+        (
+            path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            pattern,
+        )
+    };
+    let mut base = PathBuf::new();
+    let mut directories = vec![];
+    for component in parent.components() {
+        let name = component.as_os_str().to_string_lossy();
+        match component {
+            Component::Normal(_) if name == "**" => directories.push(DirectoryPattern::Recursive),
+            _ if directories.is_empty() && !has_wildcards(&name) => base.push(component),
+            Component::Normal(_) => {
+                let pattern = Pattern::parse_with(&name, &directory_options)?;
+                directories.push(DirectoryPattern::Name(pattern));
+            }
+            _ => return Err(Error::InvalidNamePattern),
+        }
+    }
+    Ok((existing_directory(base)?, directories, pattern))
+}
+
+/// Splits template of `--regex` mode into directories and the regular expression
+///
+/// The template is split at the last `/` that is neither escaped nor inside a group
+/// or a class, so `[^/]+` and `(a|b/c)` stay in the expression. A `/` left in the
+/// expression never matches, since filenames cannot contain it
+///
+/// # Examples
+/// ```
+/// use mmv::files::files::split_regex;
+/// assert_eq!(split_regex(r"logs/[^/]+\.log"), ("logs", r"[^/]+\.log"));
+/// assert_eq!(split_regex(r"(\d+)\.txt"), ("", r"(\d+)\.txt"));
+/// ```
+pub fn split_regex(template: &str) -> (&str, &str) {
+    let (mut groups, mut classes) = (0usize, 0usize);
+    let mut separator = None;
+    let mut chars = template.char_indices().peekable();
+    while let Some((index, symbol)) = chars.next() {
+        match symbol {
+            '\\' => {
+                chars.next();
+            }
+            '[' => {
+                classes += 1;
+                chars.next_if(|&(_, symbol)| symbol == '^');
+                chars.next_if(|&(_, symbol)| symbol == ']');
+            }
+            ']' if classes > 0 => classes -= 1,
+            '(' if classes == 0 => groups += 1,
+            ')' if classes == 0 => groups = groups.saturating_sub(1),
+            '/' if classes == 0 && groups == 0 => separator = Some(index),
+            _ => {}
+        }
+    }
+    match separator {
+        Some(index) => (&template[..index], &template[index + 1..]),
+        None => ("", template),
+    }
+}
+
+/// Splits output template into base directory and output pattern
+///
+/// Output pattern starts at the first component containing a `#` marker,
+/// so it may contain `/` to place files into subdirectories of the base directory.
+/// Base directory is not required to exist
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidNamePattern`] if template does not end with a filename pattern
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+/// use mmv::files::files::split_output_template;
+/// let (base, pattern) = split_output_template("src/#1/#2.rs").unwrap();
+/// assert_eq!(base, PathBuf::from("src"));
+/// assert_eq!(pattern, "#1/#2.rs");
+/// ```
+pub fn split_output_template(template: &str) -> Result<(PathBuf, String), Error> {
+    let path = PathBuf::from(template);
+    get_filename(&path)?;
+    let mut base = PathBuf::new();
+    let mut pattern: Vec<String> = vec![];
+    for component in path.components() {
+        let name = component.as_os_str();
+        if pattern.is_empty() && !name.to_string_lossy().contains('#') {
+            base.push(name);
+            continue;
+        }
+        pattern.push(get_filename(Path::new(name))?);
+    }
+    if pattern.is_empty() {
+        pattern.push(get_filename(&path)?);
+        base.pop();
+    }
+    Ok((current_if_empty(base), pattern.join("/")))
+}
+
+/// Returns directory, empty path is treated as current directory
+fn current_if_empty(directory: PathBuf) -> PathBuf {
+    if directory.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        directory
+    }
+}
+
+/// Returns directory if it exists, empty path is treated as current directory
+fn existing_directory(directory: PathBuf) -> Result<PathBuf, Error> {
+    let directory = current_if_empty(directory);
+    if directory.is_dir() {
+        Ok(directory)
+    } else {
+        Err(Error::InvalidDirectoryPath {
+            path: directory.display().to_string(),
+        })
+    }
+}
+
+/// Finds files under `base` whose directories match `directories`
+///
+/// Returned [`MatchedFilename`]s contain paths relative to `base`
+/// and fragments captured by directory components in order. A `**` component captures
+/// the matched relative directory without trailing separator.
+///
+/// `max_depth` limits how many nested directories a single `**` can match.
+/// Every directory is visited at most once per `**`, so symbolic link loops are not followed
+///
+/// # Errors
+///
+/// Returns [`Error::IOError`] if a directory could not be read
+pub fn find_files(
+    base: &Path,
+    directories: &[DirectoryPattern],
+    max_depth: Option<usize>,
+) -> Result<MatchedFilenames, Error> {
+    let mut found = vec![];
+    walk(base, Path::new(""), directories, &[], max_depth, &mut found)?;
+    Ok(found)
+}
+
+/// Matches `directories` starting at `base/relative` and collects files into `found`
+fn walk(
+    base: &Path,
+    relative: &Path,
+    directories: &[DirectoryPattern],
+    fragments: &[OsString],
+    max_depth: Option<usize>,
+    found: &mut MatchedFilenames,
+) -> Result<(), Error> {
+    let Some((directory, rest)) = directories.split_first() else {
+        for filename in get_filenames(&base.join(relative))? {
+            let mut matched = MatchedFilename::new(relative.join(filename).into_os_string());
+            matched.fragments = fragments.to_vec();
+            found.push(matched);
+        }
+        return Ok(());
+    };
+    match directory {
+        DirectoryPattern::Name(pattern) => {
+            let mut subdirectories = get_subdirectories(&base.join(relative))?;
+            subdirectories.sort();
+            for name in subdirectories {
+                if let Some(matched) = match_filename_with(&name, pattern) {
+                    let mut fragments = fragments.to_vec();
+                    fragments.extend(matched.fragments);
+                    walk(
+                        base,
+                        &relative.join(name),
+                        rest,
+                        &fragments,
+                        max_depth,
+                        found,
+                    )?;
+                }
+            }
+        }
+        DirectoryPattern::Recursive => {
+            let mut visited = HashSet::new();
+            for nested in nested_directories(&base.join(relative), max_depth, &mut visited)? {
+                let mut fragments = fragments.to_vec();
+                fragments.push(nested.clone().into_os_string());
+                let next = join_relative(relative, &nested);
+                walk(base, &next, rest, &fragments, max_depth, found)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns `directory` itself and its nested directories as paths relative to it
+///
+/// Directories already in `visited` (compared by canonical path) are skipped
+fn nested_directories(
+    directory: &Path,
+    max_depth: Option<usize>,
+    visited: &mut HashSet<PathBuf>,
+) -> Result<Vec<PathBuf>, Error> {
+    let canonical = fs::canonicalize(directory).map_err(|error| Error::IOError { error })?;
+    if !visited.insert(canonical) {
+        return Ok(vec![]);
+    }
+    let mut nested = vec![PathBuf::new()];
+    if max_depth == Some(0) {
+        return Ok(nested);
+    }
+    let mut subdirectories = get_subdirectories(directory)?;
+    subdirectories.sort();
+    for subdirectory in subdirectories {
+        let deeper = nested_directories(
+            &directory.join(&subdirectory),
+            max_depth.map(|depth| depth - 1),
+            visited,
+        )?;
+        nested.extend(
+            deeper
+                .into_iter()
+                .map(|path| join_relative(Path::new(&subdirectory), &path)),
+        );
+    }
+    Ok(nested)
+}
+
+/// Joins relative paths without adding a trailing separator when `path` is empty
+fn join_relative(base: &Path, path: &Path) -> PathBuf {
+    if path.as_os_str().is_empty() {
+        base.to_path_buf()
+    } else {
+        base.join(path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{get_directory, get_filename, get_filenames};
-    use std::{ffi::OsString, path::PathBuf};
+    use super::{
+        find_files, get_directory, get_filename, get_filenames, split_input_template,
+        split_output_template, split_regex, DirectoryPattern,
+    };
+    use crate::filename_operations::pattern::{Pattern, PatternOptions};
+    use std::{ffi::OsString, fs, path::PathBuf};
 
     use crate::files::tests::FileManager;
 
@@ -127,15 +451,126 @@ mod tests {
         let check = |subdirectory: &str, files: Vec<&str>| {
             let _submanager = manager.create_subdirectory(PathBuf::from(subdirectory).as_path());
             manager.create_files(&files);
+            let mut filenames = get_filenames(manager.get_directory()).unwrap();
+            filenames.sort();
             assert_eq!(
-                get_filenames(manager.get_directory()).unwrap().sort(),
-                files
-                    .into_iter()
-                    .map(|file| OsString::from(file))
-                    .collect::<Vec<_>>()
-                    .sort()
+                filenames,
+                files.into_iter().map(OsString::from).collect::<Vec<_>>()
             )
         };
         check("boo/", vec!["foo", "moo"]);
     }
+
+    #[test]
+    fn test_split_templates() {
+        let options = PatternOptions::default();
+        let (base, directories, pattern) =
+            split_input_template("src/**/files/*.rs", &options).unwrap();
+        assert_eq!(base, PathBuf::from("src"));
+        assert!(matches!(
+            directories[..],
+            [DirectoryPattern::Recursive, DirectoryPattern::Name(_)]
+        ));
+        assert_eq!(pattern, "*.rs");
+        let (base, directories, _) = split_input_template("src/*_ops/x/*.rs", &options).unwrap();
+        assert_eq!(base, PathBuf::from("src"));
+        assert_eq!(directories.len(), 2);
+        let (base, directories, _) = split_input_template("*.rs", &options).unwrap();
+        assert_eq!(base, PathBuf::from("."));
+        assert!(directories.is_empty());
+        assert!(split_input_template("src/**/../*.rs", &options).is_err());
+        assert!(split_input_template("missing/**/*.rs", &options).is_err());
+        assert!(split_input_template("src/{a/*.rs", &options).is_err());
+
+        let regex = PatternOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let (base, directories, pattern) =
+            split_input_template(r"src/*/[^/]+\.rs", &regex).unwrap();
+        assert_eq!(base, PathBuf::from("src"));
+        assert_eq!(directories.len(), 1);
+        assert_eq!(pattern, r"[^/]+\.rs");
+        let (base, _, pattern) = split_input_template(r"src/(a|b/c)", &regex).unwrap();
+        assert_eq!(base, PathBuf::from("src"));
+        assert_eq!(pattern, "(a|b/c)");
+        assert_eq!(split_regex(r"a\/b"), ("", r"a\/b"));
+        assert_eq!(split_regex(r"a/[]/]b"), ("a", r"[]/]b"));
+        assert_eq!(split_regex(r"a/[^]/]b"), ("a", r"[^]/]b"));
+        assert!(split_input_template("src/", &regex).is_err());
+
+        assert_eq!(
+            split_output_template("src/name.rs").unwrap(),
+            (PathBuf::from("src"), String::from("name.rs"))
+        );
+        assert_eq!(
+            split_output_template("#1/#2").unwrap(),
+            (PathBuf::from("."), String::from("#1/#2"))
+        );
+    }
+
+    #[test]
+    fn test_find_files() {
+        let manager = FileManager::from("find_files");
+        let root = manager.get_directory();
+        for directory in ["a/b/c", "d"] {
+            fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        for file in ["top", "a/b/deep", "a/b/c/deeper", "d/other"] {
+            fs::write(root.join(file), file).unwrap();
+        }
+        let check = |directories: Vec<DirectoryPattern>, max_depth, expected: Vec<(&str, &str)>| {
+            let mut found: Vec<(OsString, Vec<OsString>)> =
+                find_files(root, &directories, max_depth)
+                    .unwrap()
+                    .into_iter()
+                    .map(|matched| (matched.filename, matched.fragments))
+                    .collect();
+            found.sort();
+            let expected: Vec<(OsString, Vec<OsString>)> = expected
+                .into_iter()
+                .map(|(filename, fragment)| (filename.into(), vec![fragment.into()]))
+                .collect();
+            assert_eq!(found, expected);
+        };
+        check(
+            vec![DirectoryPattern::Recursive],
+            None,
+            vec![
+                ("a/b/c/deeper", "a/b/c"),
+                ("a/b/deep", "a/b"),
+                ("d/other", "d"),
+                ("top", ""),
+            ],
+        );
+        check(
+            vec![DirectoryPattern::Recursive],
+            Some(1),
+            vec![("d/other", "d"), ("top", "")],
+        );
+        let name = |pattern: &str| DirectoryPattern::Name(Pattern::parse(pattern).unwrap());
+        check(
+            vec![name("a"), DirectoryPattern::Recursive],
+            Some(1),
+            vec![("a/b/deep", "b")],
+        );
+        check(vec![name("?")], None, vec![("d/other", "d")]);
+        let found = find_files(root, &[name("*"), name("*")], None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].filename, "a/b/deep");
+        assert_eq!(found[0].fragments, vec!["a", "b"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_files_symlink_loop() {
+        let manager = FileManager::from("find_files_symlink_loop");
+        let root = manager.get_directory();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("a/file"), "file").unwrap();
+        std::os::unix::fs::symlink("..", root.join("a/loop")).unwrap();
+        let found = find_files(root, &[DirectoryPattern::Recursive], None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].filename, "a/file");
+    }
 }
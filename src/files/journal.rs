@@ -0,0 +1,698 @@
+//! Journal of executed moves used to undo a run
+//!
+//! A journal is a stream of YAML documents in the state directory. The first document
+//! is a [`Header`] with every planned step, it is written before the first move.
+//! Each following document is a [`Record`] appended as the run goes on.
+//! Every document is flushed to disk before the run continues, so the journal of an
+//! interrupted run tells which steps were done. A crash while a record is written
+//! leaves it incomplete, such a last record is ignored and cut off before appending
+use super::backup::Backup;
+use super::conflict::OnConflict;
+use super::plan::{normalize, Step};
+use super::transfer::Operation;
+use crate::errors::CommonError as Error;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Serialization of paths that are not valid UTF-8
+///
+/// Paths are written as text when possible and as a sequence of bytes otherwise
+mod raw_path {
+    use crate::filename_operations::encoding::{from_bytes, to_bytes};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::path::{Path, PathBuf};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum RawPath {
+        Text(String),
+        Bytes(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        match path.to_str() {
+            Some(text) => RawPath::Text(text.to_string()),
+            None => RawPath::Bytes(to_bytes(path.as_os_str()).into_owned()),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        Ok(match RawPath::deserialize(deserializer)? {
+            RawPath::Text(text) => PathBuf::from(text),
+            RawPath::Bytes(bytes) => PathBuf::from(from_bytes(bytes)),
+        })
+    }
+}
+
+/// A planned step with absolute paths
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    #[serde(with = "raw_path")]
+    pub source: PathBuf,
+    #[serde(with = "raw_path")]
+    pub target: PathBuf,
+}
+
+/// Options of a run that a resumed run must use
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct RecordedOptions {
+    #[serde(default)]
+    pub operation: Operation,
+    #[serde(default)]
+    pub backup: Option<Backup>,
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+    #[serde(default)]
+    pub create_directories: bool,
+}
+
+/// Path of a journal that could not be loaded and the error
+pub type SkippedJournal = (PathBuf, Error);
+
+/// First document of a journal
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Header {
+    pub id: String,
+    pub timestamp: SystemTime,
+    #[serde(flatten)]
+    pub options: RecordedOptions,
+    pub steps: Vec<Entry>,
+}
+
+/// Event appended to a journal
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum Record {
+    /// Step with the given index is done, the target has the given size and
+    /// modification time
+    Done {
+        step: usize,
+        size: u64,
+        modified: SystemTime,
+    },
+    /// Target of the step with the given index existed and was renamed to the backup
+    BackedUp {
+        step: usize,
+        #[serde(with = "raw_path")]
+        backup: PathBuf,
+    },
+    /// Step with the given index was reversed by a rollback, its backup is restored
+    Reverted { step: usize },
+    /// Missing directory was created for a target
+    CreatedDirectory {
+        #[serde(with = "raw_path")]
+        path: PathBuf,
+    },
+    /// Every step is done
+    Finished,
+    /// The run was undone or rolled back
+    Undone,
+}
+
+/// Journal that is being written
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    header: Header,
+}
+
+/// Wraps errors of the file system
+fn io_error(error: std::io::Error) -> Error {
+    Error::IOError { error }
+}
+
+/// Returns path of the journal with the given id
+pub fn journal_path(directory: &Path, id: &str) -> PathBuf {
+    directory.join(format!("{}.yaml", id))
+}
+
+/// Returns default directory for journals, `$XDG_STATE_HOME/mmv`
+///
+/// Falls back to `~/.local/state/mmv` and to the temporary directory
+pub fn default_state_directory() -> PathBuf {
+    let home = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"));
+    std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or(home)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("mmv")
+}
+
+impl Journal {
+    /// Creates a new journal in `directory` and writes planned steps and options
+    /// of the run to it
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the journal could not be written
+    pub fn create(
+        directory: &Path,
+        steps: &[Step],
+        options: RecordedOptions,
+    ) -> Result<Self, Error> {
+        fs::create_dir_all(directory).map_err(io_error)?;
+        let timestamp = SystemTime::now();
+        let millis = timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        let mut header = Header {
+            id: format!("{}-{}", millis, process::id()),
+            timestamp,
+            options,
+            steps: steps
+                .iter()
+                .map(|step| Entry {
+                    source: normalize(&step.source),
+                    target: normalize(&step.target),
+                })
+                .collect(),
+        };
+        let mut attempt = 0;
+        let (path, file) = loop {
+            let path = journal_path(directory, &header.id);
+            match OpenOptions::new().create_new(true).append(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                    attempt += 1;
+                    header.id = format!("{}-{}-{}", millis, process::id(), attempt);
+                }
+                Err(error) => return Err(io_error(error)),
+            }
+        };
+        let mut journal = Self { path, file, header };
+        journal.append(&journal.header.clone())?;
+        File::open(directory)
+            .and_then(|directory| directory.sync_all())
+            .map_err(io_error)?;
+        Ok(journal)
+    }
+
+    /// Opens a loaded journal to append records
+    ///
+    /// An incomplete last record is cut off first, see [`LoadedJournal::load`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the journal could not be opened or cut
+    pub fn open(journal: LoadedJournal) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&journal.path)
+            .map_err(io_error)?;
+        if file.metadata().map_err(io_error)?.len() > journal.length {
+            file.set_len(journal.length)
+                .and_then(|_| file.sync_data())
+                .map_err(io_error)?;
+        }
+        Ok(Self {
+            path: journal.path,
+            file,
+            header: journal.header,
+        })
+    }
+
+    /// Returns id of the journal
+    pub fn id(&self) -> &str {
+        &self.header.id
+    }
+
+    /// Appends a YAML document to the journal and flushes it to disk
+    fn append<T: Serialize>(&mut self, document: &T) -> Result<(), Error> {
+        let yaml = serde_yaml::to_string(document).map_err(|error| Error::InvalidJournal {
+            path: self.path.display().to_string(),
+            error: error.to_string(),
+        })?;
+        self.file
+            .write_all(format!("---\n{}", yaml).as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .map_err(io_error)
+    }
+
+    /// Records that the step with the given index is done
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the target could not be inspected or the record
+    /// could not be written
+    pub fn done(&mut self, step: usize) -> Result<(), Error> {
+        let metadata = fs::symlink_metadata(&self.header.steps[step].target).map_err(io_error)?;
+        self.append(&Record::Done {
+            step,
+            size: metadata.len(),
+            modified: metadata.modified().map_err(io_error)?,
+        })
+    }
+
+    /// Records that the target of the step with the given index was kept as `backup`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the record could not be written
+    pub fn backed_up(&mut self, step: usize, backup: &Path) -> Result<(), Error> {
+        self.append(&Record::BackedUp {
+            step,
+            backup: normalize(backup),
+        })
+    }
+
+    /// Records that a missing target directory was created
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the record could not be written
+    pub fn created_directory(&mut self, path: &Path) -> Result<(), Error> {
+        self.append(&Record::CreatedDirectory {
+            path: normalize(path),
+        })
+    }
+
+    /// Records that the step with the given index was reversed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the record could not be written
+    pub fn reverted(&mut self, step: usize) -> Result<(), Error> {
+        self.append(&Record::Reverted { step })
+    }
+
+    /// Records that every step is done
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the record could not be written
+    pub fn finished(&mut self) -> Result<(), Error> {
+        self.append(&Record::Finished)
+    }
+
+    /// Records that the run was undone
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the record could not be written
+    pub fn undone(&mut self) -> Result<(), Error> {
+        self.append(&Record::Undone)
+    }
+
+    /// Removes the journal
+    pub fn remove(self) -> bool {
+        fs::remove_file(self.path).is_ok()
+    }
+}
+
+/// Removes finished and undone journals in `directory` except the `keep` newest ones
+///
+/// Journals of interrupted runs are never removed. Returns the number of removed journals
+///
+/// # Errors
+///
+/// Propagates errors of [`LoadedJournal::load_all`]
+///
+/// Returns [`Error::IOError`] if a journal could not be removed
+pub fn prune_journals(directory: &Path, keep: usize) -> Result<usize, Error> {
+    let closed: Vec<LoadedJournal> = LoadedJournal::load_all(directory)?
+        .0
+        .into_iter()
+        .filter(|journal| !journal.is_unfinished())
+        .collect();
+    let excess = closed.len().saturating_sub(keep);
+    for journal in &closed[..excess] {
+        fs::remove_file(&journal.path).map_err(io_error)?;
+    }
+    Ok(excess)
+}
+
+/// Journal read from disk
+#[derive(Debug, Clone)]
+pub struct LoadedJournal {
+    pub path: PathBuf,
+    pub header: Header,
+    pub records: Vec<Record>,
+    /// Length in bytes of the complete documents
+    pub length: u64,
+}
+
+/// Splits a journal into documents, each starts with a `---` line
+///
+/// Bytes are split before they are decoded, so a record cut in the middle
+/// of a character does not affect the documents before it
+fn split_documents(bytes: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut starts: Vec<usize> = (0..bytes.len())
+        .filter(|&start| {
+            bytes[start..].starts_with(b"---\n") && (start == 0 || bytes[start - 1] == b'\n')
+        })
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    let ends = starts.iter().skip(1).copied().chain([bytes.len()]);
+    starts
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| (start, &bytes[start..end]))
+        .collect()
+}
+
+impl LoadedJournal {
+    /// Reads the journal at `path`
+    ///
+    /// The last record is ignored if it cannot be decoded or parsed, it was not
+    /// completely written before a crash. [`LoadedJournal::length`] ends before such a record
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the journal could not be read and
+    /// [`Error::InvalidJournal`] if it could not be parsed
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let bytes = fs::read(path).map_err(io_error)?;
+        let invalid = |error: String| Error::InvalidJournal {
+            path: path.display().to_string(),
+            error,
+        };
+        let parse = |document: &[u8]| -> Result<Record, String> {
+            let text = std::str::from_utf8(document).map_err(|error| error.to_string())?;
+            serde_yaml::from_str(text).map_err(|error| error.to_string())
+        };
+        let documents = split_documents(&bytes);
+        let (_, header) = documents
+            .first()
+            .filter(|(_, header)| !header.trim_ascii().is_empty())
+            .ok_or_else(|| invalid(String::from("journal is empty")))?;
+        let header = std::str::from_utf8(header).map_err(|error| invalid(error.to_string()))?;
+        let header: Header =
+            serde_yaml::from_str(header).map_err(|error| invalid(error.to_string()))?;
+        let mut records = vec![];
+        let mut length = bytes.len();
+        for (index, &(start, document)) in documents.iter().enumerate().skip(1) {
+            match parse(document) {
+                Ok(record) => records.push(record),
+                Err(_) if index + 1 == documents.len() => length = start,
+                Err(error) => return Err(invalid(error)),
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            header,
+            records,
+            length: length as u64,
+        })
+    }
+
+    /// Returns all journals in `directory`, oldest first, and journals that were skipped
+    ///
+    /// Journals that could not be read or parsed are returned with their errors
+    /// instead of failing, so a broken file does not stop every following run
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if the entries of the directory could not be read
+    pub fn load_all(directory: &Path) -> Result<(Vec<Self>, Vec<SkippedJournal>), Error> {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return Ok((vec![], vec![]));
+        };
+        let mut journals = vec![];
+        let mut skipped = vec![];
+        for entry in entries {
+            let path = entry.map_err(io_error)?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "yaml")
+            {
+                match Self::load(&path) {
+                    Ok(journal) => journals.push(journal),
+                    Err(error) => skipped.push((path, error)),
+                }
+            }
+        }
+        journals.sort_by_key(|journal| journal.header.timestamp);
+        skipped.sort_by(|(first, _), (second, _)| first.cmp(second));
+        Ok((journals, skipped))
+    }
+
+    /// Returns id of the journal
+    pub fn id(&self) -> &str {
+        &self.header.id
+    }
+
+    /// Returns `true` if the run was undone
+    pub fn is_undone(&self) -> bool {
+        self.records.contains(&Record::Undone)
+    }
+
+    /// Returns `true` if the run was interrupted, so it is neither finished nor undone
+    pub fn is_unfinished(&self) -> bool {
+        !self.records.contains(&Record::Finished) && !self.is_undone()
+    }
+
+    /// Returns recorded steps that are done and not reversed, in order of execution,
+    /// with the recorded size and modification time of the target
+    fn recorded(&self) -> Vec<(usize, u64, SystemTime)> {
+        let mut done: Vec<(usize, u64, SystemTime)> = vec![];
+        for record in &self.records {
+            match record {
+                Record::Done {
+                    step,
+                    size,
+                    modified,
+                } => done.push((*step, *size, *modified)),
+                Record::Reverted { step } => done.retain(|(done, ..)| done != step),
+                Record::BackedUp { .. }
+                | Record::CreatedDirectory { .. }
+                | Record::Finished
+                | Record::Undone => {}
+            }
+        }
+        done
+    }
+
+    /// Returns steps with backups of their targets that are not restored yet,
+    /// in order of execution
+    pub fn backups(&self) -> Vec<(&Entry, &Path)> {
+        let mut backups: Vec<(usize, &Path)> = vec![];
+        for record in &self.records {
+            match record {
+                Record::BackedUp { step, backup } => backups.push((*step, backup)),
+                Record::Reverted { step } => backups.retain(|(backed_up, _)| backed_up != step),
+                _ => {}
+            }
+        }
+        backups
+            .into_iter()
+            .map(|(step, backup)| (&self.header.steps[step], backup))
+            .collect()
+    }
+
+    /// Returns directories created by the run in order of creation
+    pub fn created_directories(&self) -> Vec<&Path> {
+        self.records
+            .iter()
+            .filter_map(|record| match record {
+                Record::CreatedDirectory { path } => Some(path.as_path()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns index of the first step that is not recorded as done
+    pub fn next_step(&self) -> usize {
+        self.recorded()
+            .iter()
+            .map(|(step, ..)| step + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns index of the step the run was interrupted at if the step is already done
+    ///
+    /// Steps are executed one by one, so only the step after the last recorded one
+    /// may be done without a record: its target exists and, if files are moved,
+    /// its source is gone
+    pub fn interrupted_step(&self) -> Option<usize> {
+        let step = self.next_step();
+        let entry = self.header.steps.get(step)?;
+        let source_kept = fs::symlink_metadata(&entry.source).is_ok();
+        let done = fs::symlink_metadata(&entry.target).is_ok()
+            && source_kept != (self.header.options.operation == Operation::Move);
+        (self.is_unfinished() && done).then_some(step)
+    }
+
+    /// Returns completed steps with the size and modification time of the target
+    ///
+    /// The step an unfinished run was interrupted at is included with the current
+    /// size and modification time, see [`LoadedJournal::interrupted_step`]
+    pub fn completed(&self) -> Vec<(&Entry, u64, SystemTime)> {
+        let mut completed: Vec<_> = self
+            .recorded()
+            .into_iter()
+            .map(|(step, size, modified)| (&self.header.steps[step], size, modified))
+            .collect();
+        if let Some(step) = self.interrupted_step() {
+            let entry = &self.header.steps[step];
+            if let Ok((size, modified)) = fs::symlink_metadata(&entry.target)
+                .and_then(|metadata| Ok((metadata.len(), metadata.modified()?)))
+            {
+                completed.push((entry, size, modified));
+            }
+        }
+        completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prune_journals, Journal, LoadedJournal, Record, RecordedOptions};
+    use crate::files::{plan::Step, tests::FileManager, transfer::Operation};
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn write_and_load() {
+        let manager = FileManager::from("journal_write_and_load");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["b"]);
+        let steps = [Step {
+            source: root.join("a"),
+            target: root.join("b"),
+        }];
+        let state = root.join("state");
+        let mut journal = Journal::create(&state, &steps, RecordedOptions::default()).unwrap();
+        journal.done(0).unwrap();
+        journal.undone().unwrap();
+        let id = journal.id().to_string();
+        let loaded = LoadedJournal::load(&state.join(format!("{}.yaml", id))).unwrap();
+        assert_eq!(loaded.header.id, id);
+        assert!(loaded.header.steps[0].source.is_absolute());
+        assert!(matches!(loaded.records[0], Record::Done { step: 0, size, .. } if size == 16));
+        assert!(loaded.is_undone());
+        assert_eq!(loaded.completed().len(), 1);
+        assert_eq!(LoadedJournal::load_all(&state).unwrap().0.len(), 1);
+        fs::write(state.join("broken.yaml"), "steps: 1").unwrap();
+        assert!(LoadedJournal::load(&state.join("broken.yaml")).is_err());
+        let (journals, skipped) = LoadedJournal::load_all(&state).unwrap();
+        assert_eq!(journals.len(), 1);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, state.join("broken.yaml"));
+    }
+
+    #[test]
+    fn incomplete_last_record() {
+        let manager = FileManager::from("journal_incomplete_last_record");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["b", "d"]);
+        let steps = [
+            Step {
+                source: root.join("a"),
+                target: root.join("b"),
+            },
+            Step {
+                source: root.join("c"),
+                target: root.join("d"),
+            },
+        ];
+        let state = root.join("state");
+        let mut journal = Journal::create(&state, &steps, RecordedOptions::default()).unwrap();
+        journal.done(0).unwrap();
+        let path = state.join(format!("{}.yaml", journal.id()));
+        let complete = fs::metadata(&path).unwrap().len();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"---\nrecord: do")
+            .unwrap();
+        let loaded = LoadedJournal::load(&path).unwrap();
+        assert_eq!(loaded.records.len(), 1);
+        assert_eq!(loaded.length, complete);
+        assert!(loaded.is_unfinished());
+        assert_eq!(LoadedJournal::load_all(&state).unwrap().0.len(), 1);
+
+        let mut journal = Journal::open(loaded).unwrap();
+        journal.done(1).unwrap();
+        journal.finished().unwrap();
+        let loaded = LoadedJournal::load(&path).unwrap();
+        assert_eq!(loaded.records.len(), 3);
+        assert!(!loaded.is_unfinished());
+
+        let text = fs::read_to_string(&path).unwrap();
+        let broken = text.replace(
+            "---\nrecord: finished",
+            "---\nrecord: do\n---\nrecord: finished",
+        );
+        fs::write(&path, broken).unwrap();
+        assert!(LoadedJournal::load(&path).is_err());
+
+        fs::write(&path, &text).unwrap();
+        let mut torn = "---\nrecord: do\nstep: 1\ntarget: ж".as_bytes().to_vec();
+        torn.pop();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&torn)
+            .unwrap();
+        let loaded = LoadedJournal::load(&path).unwrap();
+        assert_eq!(loaded.records.len(), 3);
+        assert_eq!(loaded.length, text.len() as u64);
+    }
+
+    #[test]
+    fn prune() {
+        let manager = FileManager::from("journal_prune");
+        let state = manager.get_directory().join("state");
+        let steps = [Step {
+            source: manager.get_directory().join("a"),
+            target: manager.get_directory().join("b"),
+        }];
+        let mut ids = vec![];
+        for finished in [true, false, true, true] {
+            let mut journal = Journal::create(&state, &steps, RecordedOptions::default()).unwrap();
+            if finished {
+                journal.finished().unwrap();
+            }
+            ids.push(journal.id().to_string());
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert_eq!(prune_journals(&state, 1).unwrap(), 2);
+        let left: Vec<_> = LoadedJournal::load_all(&state)
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|journal| journal.header.id)
+            .collect();
+        assert_eq!(left, [ids[1].clone(), ids[3].clone()]);
+        assert_eq!(prune_journals(&state, 1).unwrap(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_paths() {
+        use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
+        let manager = FileManager::from("journal_non_unicode_paths");
+        let root = manager.get_directory();
+        let target = root.join(OsString::from_vec(vec![b'r', 0xe9, b's']));
+        fs::write(&target, "latin-1").unwrap();
+        let steps = [Step {
+            source: PathBuf::from("/a"),
+            target: target.clone(),
+        }];
+        let mut journal = Journal::create(
+            root,
+            &steps,
+            RecordedOptions {
+                operation: Operation::Copy,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        journal.done(0).unwrap();
+        let loaded = LoadedJournal::load(&root.join(format!("{}.yaml", journal.id()))).unwrap();
+        assert_eq!(loaded.header.steps[0].target, super::normalize(&target));
+    }
+}
@@ -1,3 +1,15 @@
+pub mod backup;
+pub mod conflict;
+pub mod directories;
+#[allow(clippy::module_inception)]
 pub mod files;
+pub mod journal;
 pub mod r#move;
-mod tests;
+pub mod plan;
+pub mod prompt;
+pub mod recovery;
+#[cfg(test)]
+pub(crate) mod tests;
+pub mod transaction;
+pub mod transfer;
+pub mod undo;
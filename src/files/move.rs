@@ -1,56 +1,516 @@
 //! Utils to move files after pattern-matching
+use super::backup::Backup;
+use super::conflict::{resolve_conflicts, OnConflict, Resolution};
+use super::journal::{prune_journals, Journal, RecordedOptions};
+use super::plan::{normalize, plan_moves, Step};
+use super::prompt::{Confirmation, Prompt, Terminal};
+use super::transaction::Transaction;
+use super::transfer::Operation;
 use crate::errors::CommonError as Error;
-use crate::filename_operations::modified::ModifiedFilenames;
-use std::fs;
-use std::path::Path;
+use crate::filename_operations::modified::{ModifiedFilename, ModifiedFilenames};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-/// Returns [`Ok`] if paths of [`ModifiedFilenames`] does **NOT** exist
+/// How much [`move_files`] prints
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Verbosity {
+    /// Nothing but errors
+    Quiet,
+    /// The plan in dry-run mode
+    #[default]
+    Normal,
+    /// Every completed move as well
+    Verbose,
+}
+
+/// How much of the matched files a successful run moved
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// Every file was moved, renamed targets included
+    #[default]
+    Complete,
+    /// Some files were skipped because their targets exist
+    Partial { skipped: usize },
+}
+
+impl Outcome {
+    /// Exit code of the command line tool: 0 if complete, 3 if partial
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Outcome::Complete => 0,
+            Outcome::Partial { .. } => 3,
+        }
+    }
+}
+
+/// Options of [`move_files`]
+#[derive(Debug, Default, Clone)]
+pub struct MoveOptions {
+    /// What to do with existing targets
+    pub on_conflict: OnConflict,
+    /// Suffix of renamed targets with [`OnConflict::Rename`], `#` is replaced with a number,
+    /// [`DEFAULT_RENAME_SUFFIX`](super::conflict::DEFAULT_RENAME_SUFFIX) if not set
+    pub rename_suffix: Option<String>,
+    /// Keep overwritten targets as backups
+    pub backup: Option<Backup>,
+    /// Create missing target directories
+    pub create_directories: bool,
+    /// Ask before every file is moved
+    pub interactive: bool,
+    /// Only print planned moves and found problems, do not touch files
+    pub dry_run: bool,
+    /// What to print while moving
+    pub verbosity: Verbosity,
+    /// Directory to write the journal of the run to, see [`Journal`]
+    pub journal_directory: Option<PathBuf>,
+    /// Number of finished journals kept after a successful run, all are kept if not set
+    pub keep_journals: Option<usize>,
+    /// What to do with files
+    pub operation: Operation,
+}
+
+impl MoveOptions {
+    /// Returns options that are recorded in the journal of the run
+    pub fn recorded(&self) -> RecordedOptions {
+        RecordedOptions {
+            operation: self.operation,
+            backup: self.backup.clone(),
+            on_conflict: self.on_conflict,
+            create_directories: self.create_directories,
+        }
+    }
+}
+
+/// Returns `true` if the target of the file already exists
 ///
-/// # Errors
+/// A target that is the source of another move is not taken, it is moved away first.
+/// Sources are kept by other operations, such targets are left to [`check_sources_kept`]
+pub fn is_taken(
+    filename: &ModifiedFilename,
+    sources: &HashSet<PathBuf>,
+    directory_in: &Path,
+    directory_out: &Path,
+    operation: Operation,
+) -> bool {
+    let target = directory_out.join(&filename.changed);
+    let vacated = sources.contains(&normalize(&target))
+        && (operation != Operation::Move
+            || normalize(&target) != normalize(&directory_in.join(&filename.origin)));
+    target.exists() && !vacated
+}
+
+/// Returns [`Error::FilenameAlreadyExists`] for every path of [`ModifiedFilenames`]
+/// that is taken, see [`is_taken`]
+fn check_if_files_exist(
+    modified: &ModifiedFilenames,
+    directory_in: &Path,
+    directory_out: &Path,
+    operation: Operation,
+) -> Vec<Error> {
+    let sources = sources(modified, directory_in);
+    modified
+        .iter()
+        .filter(|filename| is_taken(filename, &sources, directory_in, directory_out, operation))
+        .map(|filename| Error::FilenameAlreadyExists {
+            filename: filename.changed.to_string_lossy().into_owned(),
+        })
+        .collect()
+}
+
+/// Returns normalized paths of all sources
+pub fn sources(modified: &ModifiedFilenames, directory_in: &Path) -> HashSet<PathBuf> {
+    modified
+        .iter()
+        .map(|filename| normalize(&directory_in.join(&filename.origin)))
+        .collect()
+}
+
+/// Returns [`Error::OverwritesSource`] for every target that is a source
+///
+/// Operations other than [`Operation::Move`] keep sources, so such targets would
+/// be overwritten whatever [`MoveOptions::on_conflict`] is
+fn check_sources_kept(
+    modified: &ModifiedFilenames,
+    directory_in: &Path,
+    directory_out: &Path,
+) -> Vec<Error> {
+    let sources = sources(modified, directory_in);
+    modified
+        .iter()
+        .filter(|filename| sources.contains(&normalize(&directory_out.join(&filename.changed))))
+        .map(|filename| Error::OverwritesSource {
+            filename: filename.changed.to_string_lossy().into_owned(),
+        })
+        .collect()
+}
+
+/// Returns [`Error::InvalidDirectoryPath`] for every missing parent directory of targets
 ///
-/// Returns [`Error::FilenameAlreadyExists`] if path exists
-fn check_if_files_exist(modified: &ModifiedFilenames, directory_out: &Path) -> Result<(), Error> {
+/// Every directory is reported once
+fn check_if_directories_exist(modified: &ModifiedFilenames, directory_out: &Path) -> Vec<Error> {
+    let mut missing = HashSet::new();
+    let mut errors = vec![];
     for filename in modified {
         let new_path = directory_out.join(&filename.changed);
-        if new_path.exists() {
-            return Err(Error::FilenameAlreadyExists {
-                filename: filename.changed.clone(),
-            });
+        if let Some(directory) = new_path.parent() {
+            if !directory.is_dir() && missing.insert(directory.to_path_buf()) {
+                errors.push(Error::InvalidDirectoryPath {
+                    path: directory.display().to_string(),
+                });
+            }
+        }
+    }
+    errors
+}
+
+/// Returns [`Error::TargetCollision`] if several files of [`ModifiedFilenames`]
+/// would be moved to the same path
+///
+/// Groups and their sources keep the order of [`ModifiedFilenames`]
+fn check_duplicate_targets(modified: &ModifiedFilenames, directory_out: &Path) -> Option<Error> {
+    let mut groups: Vec<(PathBuf, Vec<String>)> = vec![];
+    let mut indices: HashMap<PathBuf, usize> = HashMap::new();
+    for filename in modified {
+        let target = directory_out.join(&filename.changed);
+        let index = *indices.entry(target.clone()).or_insert_with(|| {
+            groups.push((target, vec![]));
+            groups.len() - 1
+        });
+        groups[index]
+            .1
+            .push(filename.origin.to_string_lossy().into_owned());
+    }
+    let groups: Vec<_> = groups
+        .into_iter()
+        .filter(|(_, origins)| origins.len() > 1)
+        .map(|(target, origins)| (target.display().to_string(), origins))
+        .collect();
+    if groups.is_empty() {
+        None
+    } else {
+        Some(Error::TargetCollision { groups })
+    }
+}
+
+/// Runs every check of [`move_files`] and returns all found problems
+fn check_moves(
+    modified: &ModifiedFilenames,
+    directory_in: &Path,
+    directory_out: &Path,
+    options: &MoveOptions,
+) -> Vec<Error> {
+    let mut errors: Vec<Error> = check_duplicate_targets(modified, directory_out)
+        .into_iter()
+        .collect();
+    if options.on_conflict == OnConflict::Fail {
+        errors.extend(check_if_files_exist(
+            modified,
+            directory_in,
+            directory_out,
+            options.operation,
+        ));
+    }
+    if options.operation != Operation::Move {
+        errors.extend(check_sources_kept(modified, directory_in, directory_out));
+    }
+    if !options.create_directories {
+        errors.extend(check_if_directories_exist(modified, directory_out));
+    }
+    errors
+}
+
+/// Turns found problems into a single result
+pub fn into_result(mut errors: Vec<Error>) -> Result<(), Error> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(Error::Multiple { errors }),
+    }
+}
+
+/// Prints a move as `input_path -> output_path`
+pub fn print_move(input_path: &Path, output_path: &Path) {
+    println!("{} -> {}", input_path.display(), output_path.display());
+}
+
+/// Moves [`ModifiedFilenames`] from given direcrories, asking questions on the [`Terminal`],
+/// see [`move_files_with`]
+///
+/// # Errors
+///
+/// Propagates errors of [`move_files_with`]
+pub fn move_files(
+    modified: ModifiedFilenames,
+    directory_in: &Path,
+    directory_out: &Path,
+    options: &MoveOptions,
+) -> Result<Outcome, Error> {
+    move_files_with(
+        modified,
+        directory_in,
+        directory_out,
+        options,
+        &mut Terminal,
+    )
+}
+
+/// Returns files the user agreed to move, asking `verb a -> b?` about each of them
+///
+/// # Errors
+///
+/// Returns [`Error::Aborted`] if the user quits and [`Error::IOError`]
+/// if an answer could not be read
+fn confirm_moves(
+    modified: ModifiedFilenames,
+    directory_in: &Path,
+    directory_out: &Path,
+    operation: Operation,
+    prompt: &mut dyn Prompt,
+) -> Result<ModifiedFilenames, Error> {
+    let verb = match operation {
+        Operation::Move => "rename",
+        Operation::Copy => "copy",
+        Operation::HardLink | Operation::Symlink | Operation::RelativeSymlink => "link",
+    };
+    let mut confirmation = Confirmation::new(prompt);
+    let mut confirmed = vec![];
+    for filename in modified {
+        let question = format!(
+            "{} {} -> {}?",
+            verb,
+            directory_in.join(&filename.origin).display(),
+            directory_out.join(&filename.changed).display()
+        );
+        if confirmation.confirm(&question)? {
+            confirmed.push(filename);
         }
     }
-    Ok(())
+    Ok(confirmed)
 }
 
 /// Moves [`ModifiedFilenames`] from given direcrories
 ///
+/// Files are copied or linked instead if [`MoveOptions::operation`] is not
+/// [`Operation::Move`]
+///
+/// Moves are ordered by [`plan_moves`], so targets that are sources of other moves,
+/// like in `1 -> 2, 2 -> 3` or `a -> b, b -> a`, are moved away first
+///
+/// Missing target directories are created on demand if [`MoveOptions::create_directories`]
+/// is set. Moves are done in a [`Transaction`]: if a move fails, completed moves are
+/// reversed in the opposite order and created directories are removed
+///
+/// Existing targets are handled per file by [`resolve_conflicts`]: files are skipped,
+/// renamed or overwritten according to [`MoveOptions::on_conflict`], and what happened
+/// to every conflict is printed at the end unless the verbosity is [`Verbosity::Quiet`]
+///
+/// If [`MoveOptions::interactive`] is set, the prompt is asked about every file first
+/// and only confirmed files are moved. The prompt also answers [`OnConflict::Ask`]
+///
+/// Nothing is asked in dry-run mode, and the printed conflicts tell what the run would do
+///
+/// With [`Verbosity::Verbose`] every completed move is printed as soon as it is done
+///
+/// If [`MoveOptions::journal_directory`] is set, executed moves are recorded in a [`Journal`]
+/// so the run can be undone. The journal of a run that was fully rolled back is removed.
+/// If [`MoveOptions::keep_journals`] is set, older finished journals are removed after
+/// a successful run, failures to remove them are printed unless the verbosity is
+/// [`Verbosity::Quiet`]
+///
+/// If [`MoveOptions::dry_run`] is set, prints planned moves and returns problems
+/// the real run would fail with, files are not touched
+///
+/// Returns [`Outcome::Partial`] if files were skipped because their targets exist
+///
 /// # Errors
 ///
-/// If force flag is disabled returns [`Error::FilenameAlreadyExists`] if files already exist in system
+/// Returns [`Error::TargetCollision`] if several files would be moved to the same path,
+/// regardless of [`MoveOptions::on_conflict`]
 ///
-/// Propagates [`Error::IOError`] from [`fs::rename`]
-pub fn move_files(
+/// With [`OnConflict::Fail`] returns [`Error::FilenameAlreadyExists`] if files already exist in system
+///
+/// Returns [`Error::OverwritesSource`] if a target is a source and sources are kept
+///
+/// Returns [`Error::InvalidDirectoryPath`] if a target directory does not exist
+/// and directories should not be created
+///
+/// In dry-run mode returns [`Error::Multiple`] if several of the problems above are found,
+/// otherwise the first problem is returned
+///
+/// Returns [`Error::IOError`] if the journal could not be created or a conflict
+/// could not be resolved
+///
+/// Returns [`Error::MoveFailed`] with the failure and the result of the rollback
+/// if a file could not be moved or a directory could not be created
+///
+/// Returns [`Error::Aborted`] if the user quits, nothing is moved then
+pub fn move_files_with(
+    modified: ModifiedFilenames,
+    directory_in: &Path,
+    directory_out: &Path,
+    options: &MoveOptions,
+    prompt: &mut dyn Prompt,
+) -> Result<Outcome, Error> {
+    let modified = if options.interactive && !options.dry_run {
+        confirm_moves(
+            modified,
+            directory_in,
+            directory_out,
+            options.operation,
+            prompt,
+        )?
+    } else {
+        modified
+    };
+    let (modified, conflicts) =
+        resolve_conflicts(modified, directory_in, directory_out, options, prompt)?;
+    move_resolved(modified, directory_in, directory_out, options)?;
+    if options.verbosity != Verbosity::Quiet {
+        for conflict in &conflicts {
+            println!("{}", conflict);
+        }
+    }
+    let skipped = conflicts
+        .iter()
+        .filter(|conflict| conflict.resolution == Resolution::Skipped)
+        .count();
+    Ok(if skipped == 0 {
+        Outcome::Complete
+    } else {
+        Outcome::Partial { skipped }
+    })
+}
+
+/// Moves files left after conflicts are resolved, see [`move_files_with`]
+fn move_resolved(
     modified: ModifiedFilenames,
     directory_in: &Path,
     directory_out: &Path,
-    force: bool,
+    options: &MoveOptions,
 ) -> Result<(), Error> {
-    if !force {
-        check_if_files_exist(&modified, directory_out)?;
+    let problems = check_moves(&modified, directory_in, directory_out, options);
+    let steps = plan_moves(
+        modified
+            .iter()
+            .map(|filename| Step {
+                source: directory_in.join(&filename.origin),
+                target: directory_out.join(&filename.changed),
+            })
+            .collect(),
+    );
+    if options.dry_run {
+        if options.verbosity != Verbosity::Quiet {
+            for step in &steps {
+                print_move(&step.source, &step.target);
+            }
+        }
+        return into_result(problems);
     }
-    for filename in modified {
-        let input_path = directory_in.join(Path::new(&filename.origin));
-        let output_path = directory_out.join(Path::new(&filename.changed));
-        fs::rename(input_path, output_path).map_err(|error| Error::IOError { error })?;
+    if let Some(error) = problems.into_iter().next() {
+        return Err(error);
+    }
+    let mut journal = match &options.journal_directory {
+        Some(directory) => Some(Journal::create(directory, &steps, options.recorded())?),
+        None => None,
+    };
+    match execute_steps(&steps, 0, options, journal.as_mut()) {
+        Err(Error::MoveFailed {
+            origin,
+            target,
+            error,
+            completed,
+            rollback_failures,
+        }) => {
+            if let Some(journal) = journal.filter(|_| rollback_failures.is_empty()) {
+                journal.remove();
+            }
+            Err(Error::MoveFailed {
+                origin,
+                target,
+                error,
+                completed,
+                rollback_failures,
+            })
+        }
+        result => {
+            if let Some(journal) = journal.filter(|_| options.verbosity == Verbosity::Verbose) {
+                println!("Journal {}", journal.id());
+            }
+            if let (Ok(()), Some(directory), Some(keep)) =
+                (&result, &options.journal_directory, options.keep_journals)
+            {
+                match prune_journals(directory, keep) {
+                    Ok(removed) if options.verbosity == Verbosity::Verbose && removed > 0 => {
+                        println!("Removed {} old journals", removed)
+                    }
+                    Err(error) if options.verbosity != Verbosity::Quiet => {
+                        eprintln!("Could not remove old journals: {}", error)
+                    }
+                    _ => {}
+                }
+            }
+            result
+        }
+    }
+}
+
+/// Executes planned steps from `start` on in a single [`Transaction`]
+///
+/// If the journal is given, every completed step is recorded in it and the journal
+/// is marked as finished at the end
+///
+/// # Errors
+///
+/// Returns [`Error::MoveFailed`] with the failure and the result of the rollback
+/// if a step or its record fails
+pub fn execute_steps(
+    steps: &[Step],
+    start: usize,
+    options: &MoveOptions,
+    journal: Option<&mut Journal>,
+) -> Result<(), Error> {
+    let mut transaction = Transaction::new(options.operation, options.backup.clone(), journal);
+    for (index, step) in steps.iter().enumerate().skip(start) {
+        let backup = match transaction.execute(index, step) {
+            Ok(backup) => backup,
+            Err(error) => {
+                let completed = transaction.completed();
+                return Err(Error::MoveFailed {
+                    origin: step.source.display().to_string(),
+                    target: step.target.display().to_string(),
+                    error: Box::new(error),
+                    completed,
+                    rollback_failures: transaction.rollback(),
+                });
+            }
+        };
+        if options.verbosity == Verbosity::Verbose {
+            match backup {
+                Some(backup) => println!(
+                    "{} -> {} (backup {})",
+                    step.source.display(),
+                    step.target.display(),
+                    backup.display()
+                ),
+                None => print_move(&step.source, &step.target),
+            }
+        }
+    }
+    match transaction.finish() {
+        Some(journal) => journal.finished(),
+        None => Ok(()),
     }
-    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::move_files;
-    use std::{ffi::OsString, path::PathBuf};
+    use super::{move_files, move_files_with, MoveOptions, Outcome};
+    use crate::errors::CommonError as Error;
+    use crate::files::conflict::OnConflict;
+    use crate::files::transfer::Operation;
+    use std::path::PathBuf;
 
-    use crate::{filename_operations::modified::ModifiedFilename, files::tests::FileManager};
+    use crate::files::tests::{modified, FileManager};
 
     fn move_files_check(
         manager_in: &FileManager,
@@ -63,13 +523,17 @@ mod tests {
         manager_out.clear_directory();
         manager_in.create_files(&vec![origin]);
         assert!(move_files(
-            vec![ModifiedFilename {
-                origin: OsString::from(origin),
-                changed: String::from(changed)
-            }],
-            &manager_in.get_directory(),
-            &manager_out.get_directory(),
-            force
+            vec![modified(origin, changed)],
+            manager_in.get_directory(),
+            manager_out.get_directory(),
+            &MoveOptions {
+                on_conflict: if force {
+                    OnConflict::Overwrite
+                } else {
+                    OnConflict::Fail
+                },
+                ..Default::default()
+            }
         )
         .is_ok());
         assert!(manager_out.get_directory().join(changed).exists());
@@ -106,4 +570,179 @@ mod tests {
         check("***", "****");
         check("😁😁😁", "😡😡😡");
     }
+
+    #[test]
+    fn move_files_create_directories() {
+        let manager = FileManager::from("move_files_create_directories/");
+        manager.create_files(&vec!["a", "b"]);
+        let options = MoveOptions {
+            create_directories: true,
+            ..Default::default()
+        };
+        assert!(move_files(
+            vec![modified("a", "out/a")],
+            manager.get_directory(),
+            manager.get_directory(),
+            &MoveOptions::default()
+        )
+        .is_err());
+        assert!(move_files(
+            vec![modified("a", "out/nested/a")],
+            manager.get_directory(),
+            manager.get_directory(),
+            &options
+        )
+        .is_ok());
+        assert!(manager.get_directory().join("out/nested/a").exists());
+        assert!(matches!(
+            move_files(
+                vec![modified("a", "failed/nested/a")],
+                manager.get_directory(),
+                manager.get_directory(),
+                &options
+            ),
+            Err(Error::MoveFailed { completed: 0, rollback_failures, .. }) if rollback_failures.is_empty()
+        ));
+        assert!(!manager.get_directory().join("failed").exists());
+    }
+
+    #[test]
+    fn move_files_target_collision() {
+        let manager = FileManager::from("move_files_target_collision/");
+        manager.create_files(&vec!["a_x", "b_x", "c_y", "d_y", "e_z"]);
+        let result = move_files(
+            vec![
+                modified("a_x", "x"),
+                modified("c_y", "y"),
+                modified("b_x", "x"),
+                modified("e_z", "z"),
+                modified("d_y", "./y"),
+            ],
+            manager.get_directory(),
+            manager.get_directory(),
+            &MoveOptions {
+                on_conflict: OnConflict::Overwrite,
+                ..Default::default()
+            },
+        );
+        let Err(Error::TargetCollision { groups }) = result else {
+            panic!("collision is not detected");
+        };
+        let origins: Vec<_> = groups.into_iter().map(|(_, origins)| origins).collect();
+        assert_eq!(origins, [vec!["a_x", "b_x"], vec!["c_y", "d_y"]]);
+        assert!(manager.get_directory().join("e_z").exists());
+    }
+
+    #[test]
+    fn copy_files() {
+        let manager = FileManager::from("move_files_copy_files/");
+        manager.create_files(&vec!["a", "b"]);
+        let root = manager.get_directory();
+        let options = MoveOptions {
+            operation: Operation::Copy,
+            on_conflict: OnConflict::Overwrite,
+            ..Default::default()
+        };
+        move_files(vec![modified("a", "c")], root, root, &options).unwrap();
+        assert!(root.join("a").exists() && root.join("c").exists());
+        assert!(matches!(
+            move_files(
+                vec![modified("a", "b"), modified("b", "d")],
+                root,
+                root,
+                &options
+            ),
+            Err(Error::OverwritesSource { filename }) if filename == "b"
+        ));
+        assert!(!root.join("d").exists());
+    }
+
+    #[test]
+    fn link_files() {
+        let manager = FileManager::from("move_files_link_files/");
+        manager.create_files(&vec!["a", "b"]);
+        let root = manager.get_directory();
+        let options = MoveOptions {
+            operation: Operation::HardLink,
+            ..Default::default()
+        };
+        assert!(matches!(
+            move_files(vec![modified("a", "b")], root, root, &options),
+            Err(Error::FilenameAlreadyExists { .. })
+        ));
+        let options = MoveOptions {
+            on_conflict: OnConflict::Overwrite,
+            ..options
+        };
+        move_files(vec![modified("a", "b")], root, root, &options).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: a"
+        );
+        assert!(root.join("a").exists());
+    }
+
+    #[test]
+    fn dry_run_does_not_ask() {
+        let manager = FileManager::from("move_files_dry_run_does_not_ask/");
+        manager.create_files(&vec!["a", "b"]);
+        let root = manager.get_directory();
+        let options = MoveOptions {
+            interactive: true,
+            on_conflict: OnConflict::Ask,
+            dry_run: true,
+            ..Default::default()
+        };
+        let outcome =
+            move_files_with(vec![modified("a", "b")], root, root, &options, &mut vec![]).unwrap();
+        assert_eq!(outcome, Outcome::Complete);
+        assert!(root.join("a").exists());
+    }
+
+    #[test]
+    fn unchanged_names_are_not_conflicts() {
+        let manager = FileManager::from("move_files_unchanged_names/");
+        let root = manager.get_directory();
+        for on_conflict in [OnConflict::Rename, OnConflict::Skip] {
+            manager.clear_directory();
+            manager.create_files(&vec!["IMG_1.jpg", "x_2.jpg"]);
+            let options = MoveOptions {
+                on_conflict,
+                ..Default::default()
+            };
+            let outcome = move_files(
+                vec![
+                    modified("IMG_1.jpg", "IMG_1.jpg"),
+                    modified("x_2.jpg", "IMG_2.jpg"),
+                ],
+                root,
+                root,
+                &options,
+            )
+            .unwrap();
+            assert_eq!(outcome, Outcome::Complete);
+            assert!(root.join("IMG_1.jpg").exists() && root.join("IMG_2.jpg").exists());
+            assert!(!root.join("IMG_1_1.jpg").exists());
+        }
+    }
+
+    #[test]
+    fn keep_journals() {
+        let manager = FileManager::from("move_files_keep_journals/");
+        manager.create_files(&vec!["a"]);
+        let root = manager.get_directory();
+        let state = root.join("state");
+        let journals = || std::fs::read_dir(&state).unwrap().count();
+        let mut options = MoveOptions {
+            journal_directory: Some(state.clone()),
+            ..Default::default()
+        };
+        for (origin, changed) in [("a", "b"), ("b", "c")] {
+            move_files(vec![modified(origin, changed)], root, root, &options).unwrap();
+        }
+        assert_eq!(journals(), 2);
+        options.keep_journals = Some(1);
+        move_files(vec![modified("c", "d")], root, root, &options).unwrap();
+        assert_eq!(journals(), 1);
+    }
 }
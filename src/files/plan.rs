@@ -0,0 +1,177 @@
+//! Ordering of moves whose targets are sources of other moves
+//!
+//! Moves form chains like `1 -> 2, 2 -> 3` and cycles like `a -> b, b -> a`.
+//! A chain is moved from its end, a cycle is broken by moving one file to
+//! a temporary name first
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{collections::HashMap, path, process};
+
+/// A single rename from `source` to `target`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub source: PathBuf,
+    pub target: PathBuf,
+}
+
+/// Returns absolute form of the path without `.` components
+///
+/// Symbolic links are not resolved, so the path does not have to exist
+pub fn normalize(path: &Path) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Returns a free temporary path next to `source`
+fn temporary_path(source: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    loop {
+        let name = format!(
+            ".mmv-{}-{}.tmp",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = source.with_file_name(name);
+        if path.symlink_metadata().is_err() {
+            return path;
+        }
+    }
+}
+
+/// Orders steps so that no target is taken by a file that has not been moved yet
+///
+/// Targets must be unique. Steps that do not change the path are dropped,
+/// cycles are broken through temporary names in the directory of the source
+///
+/// # Examples
+/// ```
+/// use std::path::PathBuf;
+/// use mmv::files::plan::{plan_moves, Step};
+/// let step = |source: &str, target: &str| Step {
+///     source: PathBuf::from(source),
+///     target: PathBuf::from(target),
+/// };
+/// let planned = plan_moves(vec![step("1", "2"), step("2", "3")]);
+/// assert_eq!(planned, [step("2", "3"), step("1", "2")]);
+/// assert_eq!(plan_moves(vec![step("a", "b"), step("b", "a")]).len(), 3);
+/// ```
+pub fn plan_moves(steps: Vec<Step>) -> Vec<Step> {
+    let sources: HashMap<PathBuf, usize> = steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| (normalize(&step.source), index))
+        .collect();
+    let mut done = vec![false; steps.len()];
+    let blockers: Vec<Option<usize>> = steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            let target = normalize(&step.target);
+            if sources.get(&target) == Some(&index) {
+                done[index] = true;
+            }
+            sources
+                .get(&target)
+                .copied()
+                .filter(|&blocker| blocker != index)
+        })
+        .collect();
+    let mut positions: Vec<Option<usize>> = vec![None; steps.len()];
+    let mut planned = vec![];
+    for start in 0..steps.len() {
+        let mut path = vec![];
+        let mut cycle_start = None;
+        let mut current = Some(start);
+        while let Some(index) = current {
+            if done[index] {
+                break;
+            }
+            if let Some(position) = positions[index] {
+                cycle_start = Some(position);
+                break;
+            }
+            positions[index] = Some(path.len());
+            path.push(index);
+            current = blockers[index];
+        }
+        for &index in &path {
+            positions[index] = None;
+            done[index] = true;
+        }
+        let chain_end = cycle_start.unwrap_or(path.len());
+        if let Some(cycle_start) = cycle_start {
+            let cycle = &path[cycle_start..];
+            let first = &steps[cycle[0]];
+            let temporary = temporary_path(&first.source);
+            planned.push(Step {
+                source: first.source.clone(),
+                target: temporary.clone(),
+            });
+            planned.extend(cycle[1..].iter().rev().map(|&index| steps[index].clone()));
+            planned.push(Step {
+                source: temporary,
+                target: first.target.clone(),
+            });
+        }
+        planned.extend(
+            path[..chain_end]
+                .iter()
+                .rev()
+                .map(|&index| steps[index].clone()),
+        );
+    }
+    planned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan_moves, Step};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn step(source: &str, target: &str) -> Step {
+        Step {
+            source: PathBuf::from(source),
+            target: PathBuf::from(target),
+        }
+    }
+
+    /// Applies planned steps to names and checks that no name is overwritten
+    fn simulate(names: &[&str], planned: &[Step]) -> HashMap<PathBuf, String> {
+        let mut files: HashMap<PathBuf, String> = names
+            .iter()
+            .map(|name| (PathBuf::from(name), name.to_string()))
+            .collect();
+        for step in planned {
+            let content = files.remove(&step.source).expect("source is missing");
+            assert!(files.insert(step.target.clone(), content).is_none());
+        }
+        files
+    }
+
+    #[test]
+    fn chains_and_cycles() {
+        let planned = plan_moves(vec![step("1", "2"), step("2", "3"), step("3", "4")]);
+        assert_eq!(planned, [step("3", "4"), step("2", "3"), step("1", "2")]);
+
+        let planned = plan_moves(vec![step("a", "b"), step("b", "c"), step("c", "a")]);
+        assert_eq!(planned.len(), 4);
+        let files = simulate(&["a", "b", "c"], &planned);
+        assert_eq!(files[&PathBuf::from("a")], "c");
+        assert_eq!(files[&PathBuf::from("b")], "a");
+        assert_eq!(files[&PathBuf::from("c")], "b");
+
+        let planned = plan_moves(vec![step("x", "x"), step("y", "z")]);
+        assert_eq!(planned, [step("y", "z")]);
+
+        let shift: Vec<Step> = (1..=5)
+            .map(|number| step(&number.to_string(), &(number % 5 + 1).to_string()))
+            .collect();
+        let files = simulate(&["1", "2", "3", "4", "5"], &plan_moves(shift));
+        for number in 1..=5 {
+            assert_eq!(
+                files[&PathBuf::from((number % 5 + 1).to_string())],
+                number.to_string()
+            );
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Questions to the user before files are moved
+use crate::errors::CommonError as Error;
+use std::io::{self, BufRead, Write};
+
+/// Answer to a yes-or-no question about a single file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Answer {
+    Yes,
+    No,
+    /// Yes to this and every following question
+    All,
+    /// Stop before anything is done
+    Quit,
+}
+
+/// Source of answers, the terminal or a script in tests
+pub trait Prompt {
+    /// Asks the question and waits for the answer
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::Error`] if the answer could not be read
+    fn ask(&mut self, question: &str) -> io::Result<Answer>;
+}
+
+/// Asks on standard output and reads answers from standard input
+///
+/// Unknown answers are asked again, the end of input quits
+#[derive(Debug, Default)]
+pub struct Terminal;
+
+impl Prompt for Terminal {
+    fn ask(&mut self, question: &str) -> io::Result<Answer> {
+        let stdin = io::stdin();
+        loop {
+            print!("{} [y/n/a/q] ", question);
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            if stdin.lock().read_line(&mut answer)? == 0 {
+                return Ok(Answer::Quit);
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "y" | "yes" => return Ok(Answer::Yes),
+                "n" | "no" => return Ok(Answer::No),
+                "a" | "all" => return Ok(Answer::All),
+                "q" | "quit" => return Ok(Answer::Quit),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Series of questions that stops asking once [`Answer::All`] is given
+pub struct Confirmation<'a> {
+    prompt: &'a mut dyn Prompt,
+    all: bool,
+}
+
+impl<'a> Confirmation<'a> {
+    pub fn new(prompt: &'a mut dyn Prompt) -> Self {
+        Self { prompt, all: false }
+    }
+
+    /// Returns `true` if the question is answered with yes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Aborted`] if the user quits and [`Error::IOError`]
+    /// if the answer could not be read
+    pub fn confirm(&mut self, question: &str) -> Result<bool, Error> {
+        if self.all {
+            return Ok(true);
+        }
+        match self
+            .prompt
+            .ask(question)
+            .map_err(|error| Error::IOError { error })?
+        {
+            Answer::Yes => Ok(true),
+            Answer::No => Ok(false),
+            Answer::All => {
+                self.all = true;
+                Ok(true)
+            }
+            Answer::Quit => Err(Error::Aborted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Answer, Confirmation, Prompt};
+    use crate::errors::CommonError as Error;
+    use std::io;
+
+    impl Prompt for Vec<Answer> {
+        fn ask(&mut self, _: &str) -> io::Result<Answer> {
+            Ok(self.remove(0))
+        }
+    }
+
+    #[test]
+    fn confirmation() {
+        let mut answers = vec![Answer::Yes, Answer::No, Answer::All];
+        let mut confirmation = Confirmation::new(&mut answers);
+        assert!(confirmation.confirm("first").unwrap());
+        assert!(!confirmation.confirm("second").unwrap());
+        assert!(confirmation.confirm("third").unwrap());
+        assert!(confirmation.confirm("fourth").unwrap());
+        let mut answers = vec![Answer::Quit];
+        assert!(matches!(
+            Confirmation::new(&mut answers).confirm("first"),
+            Err(Error::Aborted)
+        ));
+    }
+}
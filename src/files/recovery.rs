@@ -0,0 +1,244 @@
+//! Recovery of runs interrupted before their journal was finished
+use super::conflict::OnConflict;
+use super::journal::{Header, Journal, LoadedJournal};
+use super::plan::Step;
+use super::r#move::{execute_steps, into_result, print_move, MoveOptions, Verbosity};
+use super::transfer::Operation;
+use super::undo::{find_journal, revert};
+use crate::errors::CommonError as Error;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Returns [`Ok`] if no run recorded in `directory` was interrupted
+///
+/// # Errors
+///
+/// Returns [`Error::UnfinishedJournal`] with the oldest interrupted run
+pub fn check_unfinished(directory: &Path) -> Result<(), Error> {
+    match LoadedJournal::load_all(directory)?
+        .0
+        .into_iter()
+        .find(LoadedJournal::is_unfinished)
+    {
+        Some(journal) => Err(Error::UnfinishedJournal {
+            id: journal.header.id,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Returns `options` with the options recorded in the header of the interrupted run
+///
+/// # Errors
+///
+/// Returns [`Error::ResumeOptionsMismatch`] if `options` set the operation, backups,
+/// conflict resolution or creation of directories differently from the run
+fn recorded_options(header: &Header, options: &MoveOptions) -> Result<MoveOptions, Error> {
+    let mismatch = |option: &str| Error::ResumeOptionsMismatch {
+        id: header.id.clone(),
+        option: option.to_string(),
+    };
+    let recorded = &header.options;
+    if options.operation != Operation::Move && options.operation != recorded.operation {
+        return Err(mismatch("--copy, --link or --symlink"));
+    }
+    if options.backup.is_some() && options.backup != recorded.backup {
+        return Err(mismatch("--backup"));
+    }
+    if options.on_conflict != OnConflict::Fail && options.on_conflict != recorded.on_conflict {
+        return Err(mismatch("--on-conflict or --force"));
+    }
+    if options.create_directories && !recorded.create_directories {
+        return Err(mismatch("--mkdir"));
+    }
+    Ok(MoveOptions {
+        operation: recorded.operation,
+        backup: recorded.backup.clone(),
+        on_conflict: recorded.on_conflict,
+        create_directories: recorded.create_directories,
+        ..options.clone()
+    })
+}
+
+/// Finishes the interrupted run with the given id or the latest interrupted run
+///
+/// The step the run was interrupted at is recorded if its file was already moved,
+/// remaining steps are executed with the operation, backups, conflict resolution and
+/// creation of directories recorded for the run like in
+/// [`move_files`](super::r#move::move_files)
+///
+/// # Errors
+///
+/// Propagates errors of [`find_journal`] and [`recorded_options`]
+///
+/// Unless the run resolved conflicts with [`OnConflict::Overwrite`] returns
+/// [`Error::FilenameAlreadyExists`] for targets of remaining steps that were taken
+/// after the interruption
+///
+/// Propagates [`Error::MoveFailed`] from [`execute_steps`]
+pub fn resume(directory: &Path, id: Option<&str>, options: &MoveOptions) -> Result<(), Error> {
+    let loaded = find_journal(directory, id, true)?;
+    let options = &recorded_options(&loaded.header, options)?;
+    let steps: Vec<Step> = loaded
+        .header
+        .steps
+        .iter()
+        .map(|entry| Step {
+            source: entry.source.clone(),
+            target: entry.target.clone(),
+        })
+        .collect();
+    let interrupted = loaded.interrupted_step();
+    let start = interrupted.map_or(loaded.next_step(), |step| step + 1);
+    let remaining = &steps[start..];
+    let mut problems = vec![];
+    if options.on_conflict != OnConflict::Overwrite {
+        let sources: HashSet<&PathBuf> = remaining.iter().map(|step| &step.source).collect();
+        problems.extend(
+            remaining
+                .iter()
+                .filter(|step| step.target.exists() && !sources.contains(&step.target))
+                .map(|step| Error::FilenameAlreadyExists {
+                    filename: step.target.display().to_string(),
+                }),
+        );
+    }
+    if options.dry_run {
+        if options.verbosity != Verbosity::Quiet {
+            for step in remaining {
+                print_move(&step.source, &step.target);
+            }
+        }
+        return into_result(problems);
+    }
+    into_result(problems)?;
+    let mut journal = Journal::open(loaded)?;
+    if let Some(step) = interrupted {
+        journal.done(step)?;
+    }
+    execute_steps(&steps, start, options, Some(&mut journal))
+}
+
+/// Moves files of the interrupted run with the given id or of the latest
+/// interrupted run back, see [`revert`]
+///
+/// # Errors
+///
+/// Propagates errors of [`find_journal`] and [`revert`]
+pub fn rollback(directory: &Path, id: Option<&str>, options: &MoveOptions) -> Result<(), Error> {
+    revert(find_journal(directory, id, true)?, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_unfinished, resume, rollback};
+    use crate::errors::CommonError as Error;
+    use crate::files::backup::{Backup, BackupMode};
+    use crate::files::conflict::OnConflict;
+    use crate::files::journal::{Journal, RecordedOptions};
+    use crate::files::plan::Step;
+    use crate::files::r#move::MoveOptions;
+    use crate::files::tests::FileManager;
+    use std::fs;
+    use std::path::Path;
+
+    /// Starts a run of three moves and stops it after the second move,
+    /// before the second move is recorded
+    fn interrupt(root: &Path, state: &Path) {
+        fs::write(root.join("a"), "a").unwrap();
+        fs::write(root.join("b"), "b").unwrap();
+        fs::write(root.join("c"), "c").unwrap();
+        let step = |source: &str, target: &str| Step {
+            source: root.join(source),
+            target: root.join(target),
+        };
+        let steps = [step("a", "x"), step("b", "y"), step("c", "z")];
+        let mut journal = Journal::create(state, &steps, Default::default()).unwrap();
+        fs::rename(root.join("a"), root.join("x")).unwrap();
+        journal.done(0).unwrap();
+        fs::rename(root.join("b"), root.join("y")).unwrap();
+    }
+
+    #[test]
+    fn resume_and_rollback() {
+        let manager = FileManager::from("recovery_resume_and_rollback");
+        let root = manager.get_directory();
+        let state = root.join("state");
+        let options = MoveOptions::default();
+
+        interrupt(root, &state);
+        assert!(matches!(
+            check_unfinished(&state),
+            Err(Error::UnfinishedJournal { .. })
+        ));
+        resume(&state, None, &options).unwrap();
+        for (name, content) in [("x", "a"), ("y", "b"), ("z", "c")] {
+            assert_eq!(fs::read_to_string(root.join(name)).unwrap(), content);
+        }
+        assert!(check_unfinished(&state).is_ok());
+        assert!(matches!(
+            rollback(&state, None, &options),
+            Err(Error::NoInterruptedRun)
+        ));
+
+        manager.clear_directory();
+        interrupt(root, &state);
+        rollback(&state, None, &options).unwrap();
+        for name in ["a", "b", "c"] {
+            assert_eq!(fs::read_to_string(root.join(name)).unwrap(), name);
+        }
+        assert!(check_unfinished(&state).is_ok());
+    }
+
+    #[test]
+    fn resume_keeps_options() {
+        let manager = FileManager::from("recovery_resume_keeps_options");
+        let root = manager.get_directory();
+        let state = root.join("state");
+        manager.create_files(&vec!["a", "b", "y"]);
+        let steps = [
+            Step {
+                source: root.join("a"),
+                target: root.join("x"),
+            },
+            Step {
+                source: root.join("b"),
+                target: root.join("y"),
+            },
+        ];
+        let recorded = RecordedOptions {
+            on_conflict: OnConflict::Overwrite,
+            backup: Some(Backup {
+                mode: BackupMode::Simple,
+                suffix: String::from("~"),
+            }),
+            ..Default::default()
+        };
+        let mut journal = Journal::create(&state, &steps, recorded).unwrap();
+        fs::rename(root.join("a"), root.join("x")).unwrap();
+        journal.done(0).unwrap();
+
+        let skip = MoveOptions {
+            on_conflict: OnConflict::Skip,
+            ..Default::default()
+        };
+        assert!(matches!(
+            resume(&state, None, &skip),
+            Err(Error::ResumeOptionsMismatch { option, .. }) if option.contains("--on-conflict")
+        ));
+        let mkdir = MoveOptions {
+            create_directories: true,
+            ..Default::default()
+        };
+        assert!(resume(&state, None, &mkdir).is_err());
+        resume(&state, None, &MoveOptions::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("y")).unwrap(),
+            "Original name: b"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("y~")).unwrap(),
+            "Original name: y"
+        );
+    }
+}
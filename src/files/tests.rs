@@ -1,7 +1,16 @@
+use crate::filename_operations::modified::ModifiedFilename;
+use std::ffi::OsString;
 use std::fs;
 use std::fs::{create_dir_all, File};
 use std::path::{Path, PathBuf};
 
+pub fn modified(origin: &str, changed: &str) -> ModifiedFilename {
+    ModifiedFilename {
+        origin: OsString::from(origin),
+        changed: OsString::from(changed),
+    }
+}
+
 fn get_working_directory() -> PathBuf {
     PathBuf::from("./tests/test_data/unit_tests")
 }
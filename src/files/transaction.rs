@@ -0,0 +1,187 @@
+//! Moves that can be reversed if a later move fails
+use super::backup::{restore, Backup};
+use super::directories::CreatedDirectories;
+use super::journal::Journal;
+use super::plan::Step;
+use super::transfer::{reverse, transfer, Operation};
+use crate::errors::CommonError as Error;
+use std::path::PathBuf;
+
+/// Moves completed during a run, backups of their targets and directories created for them
+///
+/// Every step is done with the same [`Operation`]. Existing targets are kept as backups
+/// if [`Backup`] is given. Steps, backups and reversals are recorded in the journal
+/// if it is given
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    operation: Operation,
+    backup: Option<Backup>,
+    completed: Vec<(usize, Step, Option<PathBuf>)>,
+    created: CreatedDirectories,
+    journal: Option<&'a mut Journal>,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new(
+        operation: Operation,
+        backup: Option<Backup>,
+        journal: Option<&'a mut Journal>,
+    ) -> Self {
+        Self {
+            operation,
+            backup,
+            completed: vec![],
+            created: CreatedDirectories::new(),
+            journal,
+        }
+    }
+
+    /// Creates missing parents of the target, keeps an existing target as a backup,
+    /// moves or copies the file with [`transfer`] and remembers the step with the given index
+    ///
+    /// Returns path of the backup if one was made. The backup is restored right away
+    /// if the file could not be moved
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IOError`] if a directory could not be created, the backup could
+    /// not be made, the file could not be moved or the move could not be recorded
+    pub fn execute(&mut self, index: usize, step: &Step) -> Result<Option<PathBuf>, Error> {
+        let created = self.created.create_parent(&step.target)?;
+        if let Some(journal) = self.journal.as_mut() {
+            for directory in created {
+                journal.created_directory(directory)?;
+            }
+        }
+        let backup = match &self.backup {
+            Some(backup) => backup
+                .keep(&step.target)
+                .map_err(|error| Error::IOError { error })?,
+            None => None,
+        };
+        if let (Some(backup), Some(journal)) = (&backup, self.journal.as_mut()) {
+            journal.backed_up(index, backup)?;
+        }
+        if let Err(error) = transfer(self.operation, &step.source, &step.target) {
+            if let Some(backup) = &backup {
+                let _ = restore(backup, &step.target);
+            }
+            return Err(Error::IOError { error });
+        }
+        self.completed.push((index, step.clone(), backup.clone()));
+        if let Some(journal) = self.journal.as_mut() {
+            journal.done(index)?;
+        }
+        Ok(backup)
+    }
+
+    /// Returns number of completed moves
+    pub fn completed(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Ends the transaction keeping completed moves, returns the journal
+    pub fn finish(self) -> Option<&'a mut Journal> {
+        self.journal
+    }
+
+    /// Reverses completed steps in the opposite order, restores backups and removes
+    /// created directories
+    ///
+    /// Rollback does not stop at the first failure. Returns a description of every
+    /// move that could not be reversed, so an empty result means full success
+    pub fn rollback(mut self) -> Vec<String> {
+        let mut failures = vec![];
+        for (index, step, backup) in self.completed.into_iter().rev() {
+            let reverted = reverse(self.operation, &step.source, &step.target)
+                .and_then(|_| match &backup {
+                    Some(backup) => restore(backup, &step.target),
+                    None => Ok(()),
+                })
+                .map_err(|error| Error::IOError { error })
+                .and_then(|_| match self.journal.as_mut() {
+                    Some(journal) => journal.reverted(index),
+                    None => Ok(()),
+                });
+            if let Err(error) = reverted {
+                failures.push(format!(
+                    "{} -> {}: {}",
+                    step.target.display(),
+                    step.source.display(),
+                    error
+                ));
+            }
+        }
+        let directories: Vec<_> = self.created.directories().to_vec();
+        if !self.created.remove() {
+            for directory in directories.iter().filter(|directory| directory.exists()) {
+                failures.push(format!("{}: directory is not removed", directory.display()));
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transaction;
+    use crate::files::backup::{Backup, BackupMode};
+    use crate::files::{plan::Step, tests::FileManager, transfer::Operation};
+    use std::fs;
+
+    #[test]
+    fn rollback() {
+        let manager = FileManager::from("transaction_rollback");
+        manager.create_files(&vec!["a", "b"]);
+        let root = manager.get_directory();
+        let step = |source: &str, target: &str| Step {
+            source: root.join(source),
+            target: root.join(target),
+        };
+        let mut transaction = Transaction::new(Operation::Move, None, None);
+        transaction.execute(0, &step("a", "new/a")).unwrap();
+        transaction.execute(1, &step("b", "c")).unwrap();
+        assert!(transaction.execute(2, &step("missing", "d")).is_err());
+        assert_eq!(transaction.completed(), 2);
+        assert!(transaction.rollback().is_empty());
+        assert!(root.join("a").exists() && root.join("b").exists());
+        assert!(!root.join("new").exists() && !root.join("c").exists());
+
+        let mut transaction = Transaction::new(Operation::Move, None, None);
+        transaction.execute(0, &step("a", "new/a")).unwrap();
+        fs::remove_file(root.join("new/a")).unwrap();
+        let failures = transaction.rollback();
+        assert_eq!(failures.len(), 1);
+        assert!(!root.join("new").exists());
+    }
+
+    #[test]
+    fn rollback_restores_backups() {
+        let manager = FileManager::from("transaction_rollback_restores_backups");
+        manager.create_files(&vec!["a", "b"]);
+        let root = manager.get_directory();
+        let backup = Backup {
+            mode: BackupMode::Simple,
+            suffix: String::from("~"),
+        };
+        let mut transaction = Transaction::new(Operation::Copy, Some(backup), None);
+        let step = Step {
+            source: root.join("a"),
+            target: root.join("b"),
+        };
+        assert_eq!(
+            transaction.execute(0, &step).unwrap(),
+            Some(root.join("b~"))
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("b~")).unwrap(),
+            "Original name: b"
+        );
+        assert!(transaction.rollback().is_empty());
+        assert_eq!(
+            fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: b"
+        );
+        assert!(!root.join("b~").exists());
+    }
+}
@@ -0,0 +1,463 @@
+//! Moving, copying or linking a single file, also between file systems
+//!
+//! [`fs::rename`] cannot move a file to another file system, such moves fall back to
+//! copying the file next to the target, verifying the copy and removing the source
+use super::plan::normalize;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, FileTimes};
+use std::io::{self, BufReader, ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What is done with every matched file
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// The file is moved to the target
+    #[default]
+    Move,
+    /// The file is copied to the target, the source is kept
+    Copy,
+    /// The target is a hard link to the source
+    HardLink,
+    /// The target is a symbolic link with the absolute path of the source
+    Symlink,
+    /// The target is a symbolic link with the path of the source relative to the target
+    RelativeSymlink,
+}
+
+/// Returns a free path next to `target` for a partial copy or a previous target,
+/// told apart by `extension`
+fn temporary_path(target: &Path, extension: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    loop {
+        let name = format!(
+            ".mmv-{}-{}.{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            extension
+        );
+        let path = target.with_file_name(name);
+        if path.symlink_metadata().is_err() {
+            return path;
+        }
+    }
+}
+
+/// Returns `true` if both files have the same contents
+fn same_contents(first: &Path, second: &Path) -> io::Result<bool> {
+    let mut first = BufReader::new(File::open(first)?);
+    let mut second = BufReader::new(File::open(second)?);
+    let mut first_buffer = [0; 8192];
+    let mut second_buffer = [0; 8192];
+    loop {
+        let read = first.read(&mut first_buffer)?;
+        if read == 0 {
+            return Ok(second.read(&mut second_buffer)? == 0);
+        }
+        match second.read_exact(&mut second_buffer[..read]) {
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            result => result?,
+        }
+        if first_buffer[..read] != second_buffer[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Copies the file to `target` keeping permissions, access and modification times
+///
+/// Symbolic links are copied as links
+fn copy_with_metadata(source: &Path, target: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(source)?;
+    #[cfg(unix)]
+    if metadata.file_type().is_symlink() {
+        return std::os::unix::fs::symlink(fs::read_link(source)?, target);
+    }
+    fs::copy(source, target)?;
+    let times = FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+    File::options()
+        .append(true)
+        .open(target)
+        .or_else(|_| File::open(target))?
+        .set_times(times)
+}
+
+/// Moves the file by copying it to another file system
+///
+/// The copy is written next to the target, compared with the source and synced
+/// to the disk, then it is renamed to the target and the source is removed.
+/// A partial copy is removed if anything fails. An existing target is renamed aside
+/// until the source is removed, so if the source could not be removed the copy
+/// is removed and the previous target is put back, the file is never left twice
+///
+/// # Errors
+///
+/// Propagates errors of the file system, returns [`ErrorKind::InvalidData`]
+/// if the copy differs from the source
+pub fn copy_and_remove(source: &Path, target: &Path) -> io::Result<()> {
+    copy_and_remove_with(source, target, |source| fs::remove_file(source))
+}
+
+/// [`copy_and_remove`] that removes the source with `remove`
+fn copy_and_remove_with(
+    source: &Path,
+    target: &Path,
+    remove: impl FnOnce(&Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let partial = temporary_path(target, "part");
+    let previous = temporary_path(target, "old");
+    let mut kept = false;
+    let copied = copy_with_metadata(source, &partial)
+        .and_then(|_| {
+            if fs::symlink_metadata(source)?.file_type().is_symlink() {
+                Ok(())
+            } else if same_contents(source, &partial)? {
+                File::open(&partial)?.sync_all()
+            } else {
+                Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("copy of {} differs from the source", source.display()),
+                ))
+            }
+        })
+        .and_then(|_| {
+            if target.symlink_metadata().is_ok() {
+                fs::rename(target, &previous)?;
+                kept = true;
+            }
+            fs::rename(&partial, target)
+        });
+    if let Err(error) = copied {
+        let _ = fs::remove_file(&partial);
+        if kept {
+            let _ = fs::rename(&previous, target);
+        }
+        return Err(error);
+    }
+    match remove(source) {
+        Ok(()) if kept => fs::remove_file(&previous),
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let _ = fs::remove_file(target);
+            if kept {
+                let _ = fs::rename(&previous, target);
+            }
+            Err(error)
+        }
+    }
+}
+
+/// Copies the file keeping permissions, access and modification times
+///
+/// The copy is written next to the target and renamed into place when it is complete.
+/// [`fs::copy`] uses the fastest way the platform offers, like `copy_file_range`
+/// on Linux that lets file systems share data with reflinks
+///
+/// # Errors
+///
+/// Propagates errors of the file system, a partial copy is removed
+pub fn copy_file(source: &Path, target: &Path) -> io::Result<()> {
+    let partial = temporary_path(target, "part");
+    let copied = copy_with_metadata(source, &partial).and_then(|_| fs::rename(&partial, target));
+    if copied.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    copied
+}
+
+/// Returns absolute form of the path with `..` components resolved lexically
+fn resolve_parents(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in normalize(path).components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+/// Returns path to `path` from the directory `base`
+///
+/// Both paths are made absolute first and `..` components are resolved
+/// without looking at the file system, symbolic links are not resolved
+///
+/// # Examples
+/// ```
+/// use std::path::Path;
+/// use mmv::files::transfer::relative_path;
+/// assert_eq!(relative_path(Path::new("/a/b"), Path::new("/a/c/d")), Path::new("../c/d"));
+/// assert_eq!(relative_path(Path::new("/a"), Path::new("/a/b")), Path::new("b"));
+/// assert_eq!(relative_path(Path::new("/a/b/../c"), Path::new("/a/d")), Path::new("../d"));
+/// ```
+pub fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    let base = resolve_parents(base);
+    let path = resolve_parents(path);
+    let common = base
+        .components()
+        .zip(path.components())
+        .take_while(|(base, path)| base == path)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(path.components().skip(common));
+    relative
+}
+
+/// Creates a symbolic link at `link` that points to `original`
+fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(original, link);
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_file(original, link);
+}
+
+/// Creates a hard or symbolic link to `source` at `target`
+///
+/// The link is created next to the target and renamed into place, so an existing
+/// target is replaced only by a complete link
+///
+/// # Errors
+///
+/// Propagates errors of the file system, a partial link is removed
+pub fn link_file(operation: Operation, source: &Path, target: &Path) -> io::Result<()> {
+    let partial = temporary_path(target, "part");
+    let parent = target.parent().unwrap_or(Path::new(""));
+    let linked = match operation {
+        Operation::HardLink => fs::hard_link(source, &partial),
+        Operation::Symlink => symlink(&normalize(source), &partial),
+        Operation::RelativeSymlink => symlink(&relative_path(parent, source), &partial),
+        Operation::Move | Operation::Copy => unreachable!("{:?} is not a link", operation),
+    }
+    .and_then(|_| fs::rename(&partial, target));
+    if linked.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    linked
+}
+
+/// Moves the file, falling back to [`copy_and_remove`] if `source` and `target`
+/// are on different file systems
+///
+/// # Errors
+///
+/// Propagates errors of [`fs::rename`] and [`copy_and_remove`]
+pub fn move_file(source: &Path, target: &Path) -> io::Result<()> {
+    move_file_with(source, target, |source, target| fs::rename(source, target))
+}
+
+/// [`move_file`] that tries to move the file with `rename` first
+fn move_file_with(
+    source: &Path,
+    target: &Path,
+    rename: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    match rename(source, target) {
+        Err(error) if error.kind() == ErrorKind::CrossesDevices => copy_and_remove(source, target),
+        result => result,
+    }
+}
+
+/// Applies the operation to the file
+///
+/// # Errors
+///
+/// Propagates errors of [`move_file`], [`copy_file`] and [`link_file`]
+pub fn transfer(operation: Operation, source: &Path, target: &Path) -> io::Result<()> {
+    match operation {
+        Operation::Move => move_file(source, target),
+        Operation::Copy => copy_file(source, target),
+        Operation::HardLink | Operation::Symlink | Operation::RelativeSymlink => {
+            link_file(operation, source, target)
+        }
+    }
+}
+
+/// Reverses [`transfer`]: a moved file is moved back, a copy or a link is removed
+///
+/// # Errors
+///
+/// Propagates errors of [`move_file`] and [`fs::remove_file`]
+pub fn reverse(operation: Operation, source: &Path, target: &Path) -> io::Result<()> {
+    match operation {
+        Operation::Move => move_file(target, source),
+        Operation::Copy | Operation::HardLink | Operation::Symlink | Operation::RelativeSymlink => {
+            fs::remove_file(target)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        copy_and_remove, copy_and_remove_with, move_file, move_file_with, reverse, same_contents,
+        transfer, Operation,
+    };
+    use crate::files::tests::FileManager;
+    use std::fs;
+    use std::io::{self, ErrorKind};
+    use std::path::Path;
+
+    #[test]
+    fn copy_and_remove_keeps_metadata() {
+        let manager = FileManager::from("transfer_copy_and_remove");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["a"]);
+        let mut permissions = fs::metadata(root.join("a")).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(root.join("a"), permissions).unwrap();
+        let before = fs::metadata(root.join("a")).unwrap();
+        copy_and_remove(&root.join("a"), &root.join("b")).unwrap();
+        let after = fs::metadata(root.join("b")).unwrap();
+        assert!(!root.join("a").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(before.modified().unwrap(), after.modified().unwrap());
+        assert!(after.permissions().readonly());
+        assert!(copy_and_remove(&root.join("missing"), &root.join("c")).is_err());
+        assert_eq!(fs::read_dir(root).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn fallback_across_devices() {
+        let manager = FileManager::from("transfer_fallback_across_devices");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["a"]);
+        let crosses_devices = |_: &Path, _: &Path| Err(io::Error::from(ErrorKind::CrossesDevices));
+        move_file_with(&root.join("a"), &root.join("b"), crosses_devices).unwrap();
+        assert!(!root.join("a").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(fs::read_dir(root).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn source_not_removed() {
+        let manager = FileManager::from("transfer_source_not_removed");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["a", "taken"]);
+        let denied = |_: &Path| Err(io::Error::from(ErrorKind::PermissionDenied));
+        assert!(copy_and_remove_with(&root.join("a"), &root.join("b"), denied).is_err());
+        assert!(!root.join("b").exists());
+        assert!(copy_and_remove_with(&root.join("a"), &root.join("taken"), denied).is_err());
+        assert_eq!(
+            fs::read_to_string(root.join("taken")).unwrap(),
+            "Original name: taken"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("a")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(fs::read_dir(root).unwrap().count(), 2);
+
+        copy_and_remove(&root.join("a"), &root.join("taken")).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("taken")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(fs::read_dir(root).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn same_contents_of_truncated_copy() {
+        let manager = FileManager::from("transfer_same_contents");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["a", "b"]);
+        fs::copy(root.join("a"), root.join("copy")).unwrap();
+        fs::write(root.join("truncated"), "Original").unwrap();
+        assert!(same_contents(&root.join("a"), &root.join("copy")).unwrap());
+        assert!(!same_contents(&root.join("a"), &root.join("truncated")).unwrap());
+        assert!(!same_contents(&root.join("truncated"), &root.join("a")).unwrap());
+        assert!(!same_contents(&root.join("a"), &root.join("b")).unwrap());
+    }
+
+    #[test]
+    fn copy_and_reverse() {
+        let manager = FileManager::from("transfer_copy_and_reverse");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["a"]);
+        transfer(Operation::Copy, &root.join("a"), &root.join("b")).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(
+            fs::metadata(root.join("a")).unwrap().modified().unwrap(),
+            fs::metadata(root.join("b")).unwrap().modified().unwrap()
+        );
+        reverse(Operation::Copy, &root.join("a"), &root.join("b")).unwrap();
+        assert!(root.join("a").exists() && !root.join("b").exists());
+        assert!(transfer(Operation::Copy, &root.join("missing"), &root.join("b")).is_err());
+        assert_eq!(fs::read_dir(root).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links() {
+        use std::os::unix::fs::MetadataExt;
+        let manager = FileManager::from("transfer_links");
+        let root = manager.get_directory();
+        manager.create_files(&vec!["a", "taken"]);
+        fs::create_dir(root.join("nested")).unwrap();
+        transfer(Operation::HardLink, &root.join("a"), &root.join("taken")).unwrap();
+        assert_eq!(
+            fs::metadata(root.join("a")).unwrap().ino(),
+            fs::metadata(root.join("taken")).unwrap().ino()
+        );
+        transfer(Operation::Symlink, &root.join("a"), &root.join("nested/b")).unwrap();
+        assert!(fs::read_link(root.join("nested/b")).unwrap().is_absolute());
+        transfer(
+            Operation::RelativeSymlink,
+            &root.join("a"),
+            &root.join("nested/c"),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_link(root.join("nested/c")).unwrap(),
+            Path::new("../a")
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("nested/c")).unwrap(),
+            "Original name: a"
+        );
+        reverse(Operation::Symlink, &root.join("a"), &root.join("nested/b")).unwrap();
+        assert!(root.join("a").exists() && !root.join("nested/b").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn move_file_across_devices() {
+        use std::os::unix::fs::MetadataExt;
+        let manager = FileManager::from("transfer_across_devices");
+        let shared = std::path::Path::new("/dev/shm");
+        let root = manager.get_directory();
+        let other_device = fs::metadata(shared)
+            .is_ok_and(|shared| shared.dev() != fs::metadata(root).unwrap().dev());
+        if !other_device {
+            eprintln!("skipped: /dev/shm is on the same device as the test data");
+            return;
+        }
+        manager.create_files(&vec!["a"]);
+        let target = shared.join(format!("mmv-test-{}", std::process::id()));
+        move_file(&root.join("a"), &target).unwrap();
+        assert!(!root.join("a").exists());
+        move_file(&target, &root.join("a")).unwrap();
+        assert!(!target.exists());
+        assert_eq!(
+            fs::read_to_string(root.join("a")).unwrap(),
+            "Original name: a"
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! Reverting a run recorded in a [`Journal`]
+use super::backup::restore;
+use super::journal::{journal_path, Journal, LoadedJournal};
+use super::plan::{plan_moves, Step};
+use super::r#move::{execute_steps, into_result, print_move, MoveOptions, Verbosity};
+use super::transfer::Operation;
+use crate::errors::CommonError as Error;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// File moved by a run, from its original path to its current path
+struct MovedFile {
+    origin: PathBuf,
+    current: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Returns the journal with the given id, or the latest journal that is not undone
+///
+/// Only finished journals are returned, or only unfinished ones if `unfinished` is set
+///
+/// # Errors
+///
+/// Returns [`Error::JournalNotFound`] if there is no journal with the given id,
+/// [`Error::AlreadyUndone`] if it is already undone, [`Error::UnfinishedJournal`] or
+/// [`Error::NotInterrupted`] if it is not finished or finished against `unfinished`
+///
+/// Returns [`Error::NothingToUndo`] or [`Error::NoInterruptedRun`] if no journal is left
+pub fn find_journal(
+    directory: &Path,
+    id: Option<&str>,
+    unfinished: bool,
+) -> Result<LoadedJournal, Error> {
+    let Some(id) = id else {
+        return LoadedJournal::load_all(directory)?
+            .0
+            .into_iter()
+            .rev()
+            .find(|journal| !journal.is_undone() && journal.is_unfinished() == unfinished)
+            .ok_or(if unfinished {
+                Error::NoInterruptedRun
+            } else {
+                Error::NothingToUndo
+            });
+    };
+    let path = journal_path(directory, id);
+    if !path.exists() {
+        return Err(Error::JournalNotFound { id: id.to_string() });
+    }
+    let journal = LoadedJournal::load(&path)?;
+    let id = journal.header.id.clone();
+    if journal.is_undone() {
+        Err(Error::AlreadyUndone { id })
+    } else if journal.is_unfinished() && !unfinished {
+        Err(Error::UnfinishedJournal { id })
+    } else if !journal.is_unfinished() && unfinished {
+        Err(Error::NotInterrupted { id })
+    } else {
+        Ok(journal)
+    }
+}
+
+/// Folds completed steps into moves from original to current paths
+///
+/// Steps through temporary names and chains of steps become a single move,
+/// files that are back at their original path are dropped
+fn moved_files(journal: &LoadedJournal) -> Vec<MovedFile> {
+    let mut files: Vec<MovedFile> = vec![];
+    let mut indices: HashMap<PathBuf, usize> = HashMap::new();
+    for (entry, size, modified) in journal.completed() {
+        match indices.remove(&entry.source) {
+            Some(index) => {
+                let file = &mut files[index];
+                file.current = entry.target.clone();
+                file.size = size;
+                file.modified = modified;
+                indices.insert(entry.target.clone(), index);
+            }
+            None => {
+                indices.insert(entry.target.clone(), files.len());
+                files.push(MovedFile {
+                    origin: entry.source.clone(),
+                    current: entry.target.clone(),
+                    size,
+                    modified,
+                });
+            }
+        }
+    }
+    files.retain(|file| file.origin != file.current);
+    files
+}
+
+/// Returns `true` if the file still has the recorded size and modification time
+fn unchanged(path: &Path, size: u64, modified: SystemTime) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| {
+        metadata.len() == size && metadata.modified().is_ok_and(|time| time == modified)
+    })
+}
+
+/// Returns problems that prevent moving files back
+///
+/// A file must still have the recorded size and modification time, and its original
+/// path must be free unless another moved file occupies it
+fn check_undo(files: &[MovedFile]) -> Vec<Error> {
+    let currents: HashSet<&PathBuf> = files.iter().map(|file| &file.current).collect();
+    let mut errors = vec![];
+    for file in files {
+        if !unchanged(&file.current, file.size, file.modified) {
+            errors.push(Error::FileChanged {
+                path: file.current.display().to_string(),
+            });
+        }
+        if fs::symlink_metadata(&file.origin).is_ok() && !currents.contains(&file.origin) {
+            errors.push(Error::FilenameAlreadyExists {
+                filename: file.origin.display().to_string(),
+            });
+        }
+    }
+    errors
+}
+
+/// Returns [`Error::FileChanged`] for every backup of the journal that is gone
+fn check_backups(journal: &LoadedJournal) -> Vec<Error> {
+    journal
+        .backups()
+        .into_iter()
+        .filter(|(_, backup)| fs::symlink_metadata(backup).is_err())
+        .map(|(_, backup)| Error::FileChanged {
+            path: backup.display().to_string(),
+        })
+        .collect()
+}
+
+/// Moves backups of the journal back to the targets they were made of, latest first
+///
+/// # Errors
+///
+/// Returns [`Error::IOError`] if a backup could not be moved back
+fn restore_backups(journal: &LoadedJournal, options: &MoveOptions) -> Result<(), Error> {
+    for (entry, backup) in journal.backups().into_iter().rev() {
+        if options.dry_run {
+            if options.verbosity != Verbosity::Quiet {
+                print_move(backup, &entry.target);
+            }
+            continue;
+        }
+        restore(backup, &entry.target).map_err(|error| Error::IOError { error })?;
+        if options.verbosity == Verbosity::Verbose {
+            print_move(backup, &entry.target);
+        }
+    }
+    Ok(())
+}
+
+/// Removes files created by a journal of copies or links
+///
+/// # Errors
+///
+/// Returns [`Error::FileChanged`] for every file changed after the run
+///
+/// Returns [`Error::IOError`] if a file could not be removed
+fn remove_created(journal: &LoadedJournal, options: &MoveOptions) -> Result<(), Error> {
+    let created = journal.completed();
+    let mut problems: Vec<Error> = created
+        .iter()
+        .filter(|(entry, size, modified)| !unchanged(&entry.target, *size, *modified))
+        .map(|(entry, ..)| Error::FileChanged {
+            path: entry.target.display().to_string(),
+        })
+        .collect();
+    problems.extend(check_backups(journal));
+    if options.dry_run {
+        if options.verbosity != Verbosity::Quiet {
+            for (entry, ..) in &created {
+                println!("remove {}", entry.target.display());
+            }
+        }
+        return into_result(problems);
+    }
+    into_result(problems)?;
+    for (entry, ..) in created.into_iter().rev() {
+        fs::remove_file(&entry.target).map_err(|error| Error::IOError { error })?;
+        if options.verbosity == Verbosity::Verbose {
+            println!("remove {}", entry.target.display());
+        }
+    }
+    Ok(())
+}
+
+/// Removes directories created by the run, latest first, if they are empty
+fn remove_directories(journal: &LoadedJournal, options: &MoveOptions) {
+    for directory in journal.created_directories().into_iter().rev() {
+        if options.dry_run {
+            if options.verbosity != Verbosity::Quiet && directory.is_dir() {
+                println!("remove {}", directory.display());
+            }
+        } else if fs::remove_dir(directory).is_ok() && options.verbosity == Verbosity::Verbose {
+            println!("remove {}", directory.display());
+        }
+    }
+}
+
+/// Moves files of a journal back to their original paths
+///
+/// Moves are ordered by [`plan_moves`] and done in a single transaction, then
+/// overwritten targets are restored from their backups, directories created by
+/// the run are removed if they are empty and the journal is marked as undone.
+/// Copies and links are removed instead
+///
+/// # Errors
+///
+/// Returns [`Error::FileChanged`] if a file or a backup was changed or removed after the run and
+/// [`Error::FilenameAlreadyExists`] if its original path is taken, or [`Error::Multiple`]
+/// if several problems are found
+///
+/// Propagates [`Error::MoveFailed`] from [`execute_steps`] and errors of [`restore_backups`]
+pub fn revert(journal: LoadedJournal, options: &MoveOptions) -> Result<(), Error> {
+    if journal.header.options.operation != Operation::Move {
+        remove_created(&journal, options)?;
+        restore_backups(&journal, options)?;
+        remove_directories(&journal, options);
+        if options.dry_run {
+            return Ok(());
+        }
+        return Journal::open(journal)?.undone();
+    }
+    let options = &MoveOptions {
+        operation: Operation::Move,
+        backup: None,
+        ..options.clone()
+    };
+    let files = moved_files(&journal);
+    let mut problems = check_undo(&files);
+    problems.extend(check_backups(&journal));
+    let steps = plan_moves(
+        files
+            .into_iter()
+            .map(|file| Step {
+                source: file.current,
+                target: file.origin,
+            })
+            .collect(),
+    );
+    if options.dry_run {
+        if options.verbosity != Verbosity::Quiet {
+            for step in &steps {
+                print_move(&step.source, &step.target);
+            }
+        }
+        restore_backups(&journal, options)?;
+        remove_directories(&journal, options);
+        return into_result(problems);
+    }
+    into_result(problems)?;
+    execute_steps(&steps, 0, options, None)?;
+    restore_backups(&journal, options)?;
+    remove_directories(&journal, options);
+    Journal::open(journal)?.undone()
+}
+
+/// Undoes the finished run with the given id or the latest one that is not undone yet
+///
+/// # Errors
+///
+/// Propagates errors of [`find_journal`] and [`revert`]
+pub fn undo(directory: &Path, id: Option<&str>, options: &MoveOptions) -> Result<(), Error> {
+    revert(find_journal(directory, id, false)?, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::undo;
+    use crate::errors::CommonError as Error;
+    use crate::files::backup::{Backup, BackupMode};
+    use crate::files::conflict::OnConflict;
+    use crate::files::r#move::{move_files, MoveOptions};
+    use crate::files::tests::{modified, FileManager};
+    use std::fs;
+
+    #[test]
+    fn undo_moves() {
+        let manager = FileManager::from("undo_moves");
+        manager.create_files(&vec!["a", "b", "c"]);
+        let root = manager.get_directory();
+        let state = root.join("state");
+        let options = MoveOptions {
+            create_directories: true,
+            journal_directory: Some(state.clone()),
+            ..Default::default()
+        };
+        move_files(
+            vec![modified("a", "b"), modified("b", "a"), modified("c", "d/c")],
+            root,
+            root,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("a")).unwrap(),
+            "Original name: b"
+        );
+        undo(&state, None, &options).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("a")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: b"
+        );
+        assert!(root.join("c").exists());
+        assert!(!root.join("d").exists());
+        assert!(matches!(
+            undo(&state, None, &options),
+            Err(Error::NothingToUndo)
+        ));
+
+        move_files(vec![modified("a", "e")], root, root, &options).unwrap();
+        fs::write(root.join("e"), "changed").unwrap();
+        fs::write(root.join("a"), "taken").unwrap();
+        assert!(matches!(
+            undo(&state, None, &options),
+            Err(Error::Multiple { errors }) if errors.len() == 2
+        ));
+        assert_eq!(fs::read_to_string(root.join("e")).unwrap(), "changed");
+    }
+
+    #[test]
+    fn undo_restores_backups() {
+        let manager = FileManager::from("undo_restores_backups");
+        manager.create_files(&vec!["a", "b"]);
+        let root = manager.get_directory();
+        let state = root.join("state");
+        let options = MoveOptions {
+            on_conflict: OnConflict::Overwrite,
+            backup: Some(Backup {
+                mode: BackupMode::Numbered,
+                suffix: String::from("~"),
+            }),
+            journal_directory: Some(state.clone()),
+            ..Default::default()
+        };
+        move_files(vec![modified("a", "b")], root, root, &options).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("b.~1~")).unwrap(),
+            "Original name: b"
+        );
+        undo(&state, None, &options).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("a")).unwrap(),
+            "Original name: a"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("b")).unwrap(),
+            "Original name: b"
+        );
+        assert!(!root.join("b.~1~").exists());
+    }
+}
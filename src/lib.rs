@@ -1,6 +1,17 @@
 //! Move and rename files by filename pattern
 //!
-//! A tool to move multiple files by filename a simple regex-like pattern (only '*' available)
+//! A tool to move multiple files by a filename pattern.
+//! The input pattern may contain wildcards:
+//! - `*` and `*+` match any sequence of characters, shortest or longest first
+//! - `?` matches one character, `[abc]`, `[a-z]` and `[!0-9]` match one character of a class
+//! - `{jpg,jpeg,png}` matches one of the alternatives, braces may be nested
+//! - `**` as a whole path component matches any number of nested directories
+//! - `\` escapes the following character
+//!
+//! With `--regex` the input pattern is a regular expression instead.
+//! Every wildcard or group produces a fragment that is referenced in the output pattern
+//! as `#N` or, for named groups, as `#{name}`. A marker may have case modifiers
+//! like `#1:upper` or `#{1:upper}`, see [`filename_operations::case::Modifier`]
 //! # Example
 //! ```[bash]
 //! mmv ./*_*.txt -> ./#2_#1.tx
@@ -14,47 +25,259 @@ pub mod errors;
 pub mod filename_operations;
 pub mod files;
 
+use crate::filename_operations::edit::{default_editor, edit_targets};
 use crate::filename_operations::operations::match_and_modify_filenames;
-use crate::files::files::split_path_to_filename;
-use crate::files::r#move::move_files;
+use crate::filename_operations::pattern::{Pattern, PatternOptions};
+use crate::files::files::{split_input_template, split_output_template};
+use crate::files::r#move::{move_files_with, MoveOptions, Outcome, Verbosity};
 use clap::Parser;
 use errors::CommonError;
-use files::files::get_filenames;
+use files::backup::{Backup, BackupMode, DEFAULT_BACKUP_SUFFIX};
+use files::conflict::OnConflict;
+use files::files::find_files;
+use files::journal::{default_state_directory, LoadedJournal};
+use files::prompt::{Prompt, Terminal};
+use files::recovery::{check_unfinished, resume, rollback};
+use files::transfer::Operation;
+use files::undo::undo;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Default)]
 #[clap(
     version,
     about = "Simple program to rename multiple files by a template"
 )]
 /// Deserialized command-line arguments
 pub struct Arguments {
+    #[clap(required_unless_present_any = ["undo", "resume", "rollback"], default_value = "")]
     /// Path to directory where files lie & template of changes
     pub input_template: String,
 
+    #[clap(required_unless_present_any = ["undo", "resume", "rollback"], default_value = "")]
     /// Template of future file names
     pub output_template: String,
 
-    #[clap(short, long)]
-    /// Override existing files
+    #[clap(short, long, conflicts_with = "on_conflict")]
+    /// Override existing files, same as `--on-conflict overwrite`
     pub force: bool,
+
+    #[clap(long, value_enum, value_name = "STRATEGY")]
+    /// What to do with each file whose target exists, by default the run fails
+    pub on_conflict: Option<OnConflict>,
+
+    #[clap(long, value_name = "SUFFIX")]
+    /// Suffix inserted before the extension by `--on-conflict rename`, `#` is replaced with a number [default: _#]
+    pub rename_suffix: Option<String>,
+
+    #[clap(long, value_enum, value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "existing")]
+    /// Keep every overwritten file as a backup, numbered if numbered backups exist unless MODE is given
+    pub backup: Option<BackupMode>,
+
+    #[clap(short = 'S', long, value_name = "SUFFIX", requires = "backup")]
+    /// Suffix of simple backups [default: ~]
+    pub suffix: Option<String>,
+
+    #[clap(short = 'p', long)]
+    /// Create missing target directories
+    pub mkdir: bool,
+
+    #[clap(short, long)]
+    /// Ask before every file is moved: yes, no, all remaining or quit
+    pub interactive: bool,
+
+    #[clap(long)]
+    /// Edit targets in a text editor before files are moved
+    pub edit: bool,
+
+    #[clap(long, value_name = "COMMAND", requires = "edit")]
+    /// Editor for `--edit`, `$VISUAL` or `$EDITOR` by default.
+    /// The command is split at whitespace, quotes are not supported
+    pub editor: Option<String>,
+
+    #[clap(long)]
+    /// Treat input file name as a regular expression, named groups are referenced as #{name}
+    pub regex: bool,
+
+    #[clap(long)]
+    /// Match input template without regard to case, fragments keep the original case
+    pub ignore_case: bool,
+
+    #[clap(long, value_name = "DEPTH")]
+    /// Maximum number of nested directories a single `**` can descend into
+    pub max_depth: Option<usize>,
+
+    #[clap(short, long, conflicts_with_all = ["link", "symlink"])]
+    /// Copy files instead of moving them, sources are kept
+    pub copy: bool,
+
+    #[clap(short, long, conflicts_with = "symlink")]
+    /// Create hard links instead of moving files
+    pub link: bool,
+
+    #[clap(short, long)]
+    /// Create symbolic links with absolute paths instead of moving files
+    pub symlink: bool,
+
+    #[clap(short, long, requires = "symlink")]
+    /// Make symbolic links relative to their directory
+    pub relative: bool,
+
+    #[clap(short = 'n', long)]
+    /// Print planned moves and problems without touching files
+    pub dry_run: bool,
+
+    #[clap(short, long, conflicts_with = "quiet")]
+    /// Print every completed move
+    pub verbose: bool,
+
+    #[clap(short, long)]
+    /// Print nothing but errors
+    pub quiet: bool,
+
+    #[clap(long, value_name = "ID", num_args = 0..=1, conflicts_with_all = ["input_template", "output_template"])]
+    /// Move files of the latest run, or of the run with the given journal id, back
+    pub undo: Option<Option<String>>,
+
+    #[clap(long, value_name = "ID", num_args = 0..=1, conflicts_with_all = ["input_template", "output_template", "undo", "rollback"])]
+    /// Finish the latest interrupted run, or the interrupted run with the given journal id
+    pub resume: Option<Option<String>>,
+
+    #[clap(long, value_name = "ID", num_args = 0..=1, conflicts_with_all = ["input_template", "output_template", "undo"])]
+    /// Move files of the latest interrupted run, or of the interrupted run with the given journal id, back
+    pub rollback: Option<Option<String>>,
+
+    #[clap(long, value_name = "DIRECTORY", default_value = default_state_argument())]
+    /// Directory for journals of runs, `$XDG_STATE_HOME/mmv` by default
+    pub state_dir: Option<PathBuf>,
+
+    #[clap(long, value_name = "COUNT")]
+    /// Keep only the COUNT newest finished journals after a run, all are kept by default
+    pub keep_journals: Option<usize>,
+}
+
+/// Returns [`default_state_directory`] as a default value of [`Arguments::state_dir`]
+///
+/// Journals are written only when the directory is set, so runs of the command line tool
+/// are journaled while [`Arguments::default`] is not
+fn default_state_argument() -> &'static OsStr {
+    static DEFAULT: OnceLock<PathBuf> = OnceLock::new();
+    DEFAULT.get_or_init(default_state_directory).as_os_str()
+}
+
+/// Prints journals in `directory` that could not be loaded to stderr
+///
+/// Such journals are ignored, so runs recorded in them cannot be undone or resumed
+fn report_skipped_journals(directory: &Path) {
+    if let Ok((_, skipped)) = LoadedJournal::load_all(directory) {
+        for (path, error) in skipped {
+            eprintln!("Skipped journal {}: {}", path.display(), error);
+        }
+    }
+}
+
+/// Starts the whole program, asking questions on the [`Terminal`], see [`run_with`]
+pub fn run(arguments: Arguments) -> Result<Outcome, CommonError> {
+    run_with(arguments, &mut Terminal)
 }
 
 /// Starts the whole program
-/// - Reads filenames from the directory using [`get_filenames`]
+/// - Reads filenames from the matching directories using [`find_files`]
 /// - Changes filenames according to the pattern using [`match_and_modify_filenames`]
-/// - Renames and/or moves files using [`move_files`]
-pub fn run(arguments: Arguments) -> Result<(), CommonError> {
-    let (input_directory, input_pattern) = split_path_to_filename(&arguments.input_template)?;
-    let (output_directory, output_pattern) = split_path_to_filename(&arguments.output_template)?;
+/// - With `--edit` lets the user change targets in an editor using [`edit_targets`]
+/// - Renames and/or moves files using [`move_files_with`], `--interactive` and
+///   `--on-conflict ask` get answers from the prompt
+///
+/// With `--undo` moves files of a previous run back using [`undo`] instead.
+/// Refuses to start while a run is interrupted, such run is finished with [`resume`]
+/// or reverted with [`rollback`]
+///
+/// Returns [`Outcome::Partial`] if files were skipped because their targets exist
+pub fn run_with(arguments: Arguments, prompt: &mut dyn Prompt) -> Result<Outcome, CommonError> {
+    let move_options = MoveOptions {
+        on_conflict: match arguments.on_conflict {
+            Some(on_conflict) => on_conflict,
+            None if arguments.force => OnConflict::Overwrite,
+            None => OnConflict::Fail,
+        },
+        rename_suffix: arguments.rename_suffix.clone(),
+        backup: arguments.backup.map(|mode| Backup {
+            mode,
+            suffix: arguments
+                .suffix
+                .clone()
+                .unwrap_or_else(|| String::from(DEFAULT_BACKUP_SUFFIX)),
+        }),
+        create_directories: arguments.mkdir,
+        interactive: arguments.interactive,
+        dry_run: arguments.dry_run,
+        verbosity: if arguments.quiet {
+            Verbosity::Quiet
+        } else if arguments.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        },
+        journal_directory: arguments.state_dir.clone(),
+        keep_journals: arguments.keep_journals,
+        operation: if arguments.copy {
+            Operation::Copy
+        } else if arguments.link {
+            Operation::HardLink
+        } else if arguments.symlink && arguments.relative {
+            Operation::RelativeSymlink
+        } else if arguments.symlink {
+            Operation::Symlink
+        } else {
+            Operation::Move
+        },
+    };
+    let state_directory = arguments
+        .state_dir
+        .clone()
+        .unwrap_or_else(default_state_directory);
+    let uses_journals = arguments.state_dir.is_some()
+        || arguments.undo.is_some()
+        || arguments.resume.is_some()
+        || arguments.rollback.is_some();
+    if uses_journals && !arguments.quiet {
+        report_skipped_journals(&state_directory);
+    }
+    if let Some(id) = &arguments.resume {
+        return resume(&state_directory, id.as_deref(), &move_options).map(|_| Outcome::Complete);
+    }
+    if let Some(id) = &arguments.rollback {
+        return rollback(&state_directory, id.as_deref(), &move_options).map(|_| Outcome::Complete);
+    }
+    if arguments.state_dir.is_some() || arguments.undo.is_some() {
+        check_unfinished(&state_directory)?;
+    }
+    if let Some(id) = &arguments.undo {
+        return undo(&state_directory, id.as_deref(), &move_options).map(|_| Outcome::Complete);
+    }
+    let pattern_options = PatternOptions {
+        regex: arguments.regex,
+        ignore_case: arguments.ignore_case,
+    };
+    let (input_directory, directory_patterns, input_pattern) =
+        split_input_template(&arguments.input_template, &pattern_options)?;
+    let (output_directory, output_pattern) = split_output_template(&arguments.output_template)?;
+    let input_pattern = Pattern::parse_with(&input_pattern, &pattern_options)?;
 
-    let filenames = get_filenames(&input_directory)?;
-    let modified_filenames =
-        match_and_modify_filenames(filenames, &input_pattern, &output_pattern)?;
+    let candidates = find_files(&input_directory, &directory_patterns, arguments.max_depth)?;
+    let mut modified_filenames =
+        match_and_modify_filenames(candidates, &input_pattern, &output_pattern)?;
+    if arguments.edit {
+        let editor = arguments.editor.clone().unwrap_or_else(default_editor);
+        modified_filenames = edit_targets(modified_filenames, &editor)?;
+    }
 
-    move_files(
+    move_files_with(
         modified_filenames,
         &input_directory,
         &output_directory,
-        arguments.force,
+        &move_options,
+        prompt,
     )
 }
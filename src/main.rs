@@ -5,9 +5,10 @@ use mmv::Arguments;
 fn main() {
     let arguments = Arguments::parse();
     match run(arguments) {
-        Ok(()) => {}
+        Ok(outcome) => std::process::exit(outcome.exit_code()),
         Err(error) => {
-            println!("{:}", error);
+            eprintln!("{:}", error);
+            std::process::exit(error.exit_code());
         }
     }
 }
@@ -1,6 +1,4 @@
-use serde;
 use serde::Deserialize;
-use serde_yaml;
 use std::fs;
 use std::path::PathBuf;
 
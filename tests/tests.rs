@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use mmv::errors::CommonError as Error;
+use mmv::{run, Arguments};
 pub mod utils;
 pub use utils::file_manager::FileManager;
 use utils::{run::*, utils::*};
@@ -60,6 +61,594 @@ fn ok_special() -> Result<(), Error> {
     )?;
     Ok(())
 }
+#[test]
+fn ok_alternation() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::OkAlternation);
+    do_run(
+        &manager,
+        vec!["cat.jpg", "dog.jpeg", "fox.png"],
+        vec!["jpg_cat", "jpeg_dog", "png_fox"],
+        "*.{jpg,jpeg,png}",
+        "#2_#1",
+    )?;
+    Ok(())
+}
+
+#[test]
+fn ok_regex() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::OkRegex);
+    let directory = manager.get_directory();
+    manager.create_files(&vec!["IMG_0001.jpg", "IMG_0002.jpeg", "notes.txt"]);
+    run(Arguments {
+        input_template: directory
+            .join(r"IMG_(?<number>\d+)\.(jpe?g)")
+            .to_str()
+            .unwrap()
+            .to_string(),
+        output_template: directory.join("#{number}.#2").to_str().unwrap().to_string(),
+        regex: true,
+        ..Default::default()
+    })?;
+    assert!(directory.join("0001.jpg").exists());
+    assert!(directory.join("0002.jpeg").exists());
+    assert!(directory.join("notes.txt").exists());
+
+    let docs = manager.create_subdirectory(PathBuf::from("docs").as_path());
+    docs.create_files(&vec!["a.txt"]);
+    run(Arguments {
+        input_template: template(&manager, r"*/(?x) ([^/]+) \.txt # name and extension"),
+        output_template: template(&manager, "#1_#2.txt"),
+        regex: true,
+        ..Default::default()
+    })?;
+    assert!(directory.join("docs_a.txt").exists());
+    assert!(directory.join("notes.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn invalid_regex() {
+    let manager = FileManager::from_test_name(TestName::InvalidRegex);
+    let error = run(Arguments {
+        input_template: manager
+            .get_directory()
+            .join("IMG_(\\d+")
+            .to_str()
+            .unwrap()
+            .to_string(),
+        output_template: manager
+            .get_directory()
+            .join("#1")
+            .to_str()
+            .unwrap()
+            .to_string(),
+        regex: true,
+        ..Default::default()
+    })
+    .unwrap_err();
+    assert!(error.to_string().contains("unclosed group"));
+}
+
+#[test]
+fn recursive() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::Recursive);
+    let photos = manager.create_subdirectory(PathBuf::from("photos/2023/jan").as_path());
+    photos.create_files(&vec!["IMG_1.jpg", "notes.txt"]);
+    let flat = manager.create_subdirectory(PathBuf::from("flat").as_path());
+    manager.create_files(&vec!["photos/IMG_2.jpg"]);
+    run(Arguments {
+        input_template: template(&manager, "photos/**/IMG_*.jpg"),
+        output_template: template(&manager, "photos/#1/#2.jpg"),
+        ..Default::default()
+    })?;
+    assert!(photos.get_directory().join("1.jpg").exists());
+    assert!(manager.get_directory().join("photos/2.jpg").exists());
+    run(Arguments {
+        input_template: template(&manager, "photos/**/*.jpg"),
+        output_template: template(&manager, "flat/#2.jpg"),
+        ..Default::default()
+    })?;
+    assert!(flat.get_directory().join("1.jpg").exists());
+    assert!(flat.get_directory().join("2.jpg").exists());
+    assert!(photos.get_directory().join("notes.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn directory_wildcards() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::DirectoryWildcards);
+    let web = manager.create_subdirectory(PathBuf::from("logs/web").as_path());
+    let db = manager.create_subdirectory(PathBuf::from("logs/db").as_path());
+    web.create_files(&vec!["app_1.log"]);
+    db.create_files(&vec!["app_2.log", "other.log"]);
+    run(Arguments {
+        input_template: template(&manager, "logs/*/app_*.log"),
+        output_template: template(&manager, "logs/#1_app_#2.log"),
+        ..Default::default()
+    })?;
+    assert!(manager.get_directory().join("logs/web_app_1.log").exists());
+    assert!(manager.get_directory().join("logs/db_app_2.log").exists());
+    assert!(db.get_directory().join("other.log").exists());
+    Ok(())
+}
+
+#[test]
+fn create_directories() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::CreateDirectories);
+    manager.create_files(&vec![
+        "2023-01-05_a.jpg",
+        "2023-01-05_b.jpg",
+        "2023-02-01_c.jpg",
+    ]);
+    let arguments = |mkdir: bool| Arguments {
+        input_template: template(&manager, "*_*.jpg"),
+        output_template: template(&manager, "sorted/#1/#2.jpg"),
+        mkdir,
+        ..Default::default()
+    };
+    assert!(matches!(
+        run(arguments(false)),
+        Err(Error::InvalidDirectoryPath { .. })
+    ));
+    run(arguments(true))?;
+    let sorted = manager.get_directory().join("sorted");
+    assert!(sorted.join("2023-01-05/a.jpg").exists());
+    assert!(sorted.join("2023-01-05/b.jpg").exists());
+    assert!(sorted.join("2023-02-01/c.jpg").exists());
+    Ok(())
+}
+
+#[test]
+fn ignore_case() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::IgnoreCase);
+    manager.create_files(&vec!["Cat.JPG", "dog.jpg", "Fox.Jpg", "notes.txt"]);
+    run(Arguments {
+        input_template: template(&manager, "*.jpg"),
+        output_template: template(&manager, "img_#1.jpg"),
+        ignore_case: true,
+        ..Default::default()
+    })?;
+    for filename in ["img_Cat.jpg", "img_dog.jpg", "img_Fox.jpg", "notes.txt"] {
+        assert!(manager.get_directory().join(filename).exists());
+    }
+    Ok(())
+}
+
+#[test]
+fn ok_modifiers() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::OkModifiers);
+    do_run(
+        &manager,
+        vec!["Annual Report.PDF", "привет мир.Txt"],
+        vec!["annual-report.pdf", "привет-мир.txt"],
+        "*.*",
+        "#1:kebab.#2:lower",
+    )?;
+    do_run(
+        &manager,
+        vec!["my_holiday_photo.jpg"],
+        vec!["My_Holiday_Photo.jpg"],
+        "*.jpg",
+        "#1:title.jpg",
+    )?;
+    Ok(())
+}
+
+#[test]
+fn dry_run() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::DryRun);
+    let filenames = vec!["a.txt", "b.txt", "new_b.txt"];
+    manager.create_files(&filenames);
+    let arguments = |output: &str| Arguments {
+        input_template: template(&manager, "?.txt"),
+        output_template: template(&manager, output),
+        dry_run: true,
+        ..Default::default()
+    };
+    run(arguments("renamed_#1.txt"))?;
+    assert!(matches!(
+        run(arguments("new_#1.txt")),
+        Err(Error::FilenameAlreadyExists { filename }) if filename == "new_b.txt"
+    ));
+    assert!(matches!(
+        run(arguments("#1/#1.txt")),
+        Err(Error::Multiple { errors }) if errors.len() == 2
+    ));
+    for filename in filenames {
+        assert!(manager.get_directory().join(filename).exists());
+    }
+    assert!(!manager.get_directory().join("renamed_a.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn verbosity_flags() -> Result<(), Error> {
+    use clap::Parser;
+    let manager = FileManager::from_test_name(TestName::VerbosityFlags);
+    manager.create_files(&vec!["a.txt"]);
+    assert!(Arguments::try_parse_from(["mmv", "-v", "-q", "*", "#1"]).is_err());
+    let arguments = Arguments::try_parse_from([
+        "mmv",
+        "-v",
+        "--state-dir",
+        &template(&manager, "state"),
+        &template(&manager, "*.txt"),
+        &template(&manager, "#1.md"),
+    ])
+    .unwrap();
+    assert!(arguments.verbose && !arguments.quiet);
+    run(arguments)?;
+    assert!(manager.get_directory().join("a.md").exists());
+    Ok(())
+}
+
+#[test]
+fn target_collision() {
+    let manager = FileManager::from_test_name(TestName::TargetCollision);
+    let result = do_run(&manager, vec!["a_x", "b_x"], vec!["x", "x"], "*_*", "#2");
+    assert!(matches!(result, Err(Error::TargetCollision { groups }) if groups.len() == 1));
+    assert!(manager.get_directory().join("a_x").exists());
+    assert!(manager.get_directory().join("b_x").exists());
+}
+
+#[test]
+fn swap_names() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::SwapNames);
+    do_run(
+        &manager,
+        vec!["a_b", "b_a", "c_d"],
+        vec!["b_a", "a_b", "d_c"],
+        "*_*",
+        "#2_#1",
+    )?;
+    let leftovers = std::fs::read_dir(manager.get_directory()).unwrap().count();
+    assert_eq!(leftovers, 3);
+    Ok(())
+}
+
+#[test]
+fn undo() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::Undo);
+    manager.create_files(&vec!["a_1.txt", "b_2.txt"]);
+    let directory = manager.get_directory();
+    let state_dir = Some(directory.join("state"));
+    run(Arguments {
+        input_template: template(&manager, "*_*.txt"),
+        output_template: template(&manager, "#2/#1.txt"),
+        mkdir: true,
+        state_dir: state_dir.clone(),
+        ..Default::default()
+    })?;
+    assert!(directory.join("1/a.txt").exists());
+    let undo = |id: Option<String>| Arguments {
+        undo: Some(id),
+        state_dir: state_dir.clone(),
+        ..Default::default()
+    };
+    run(undo(None))?;
+    assert!(directory.join("a_1.txt").exists());
+    assert!(directory.join("b_2.txt").exists());
+    assert!(matches!(run(undo(None)), Err(Error::NothingToUndo)));
+    assert!(matches!(
+        run(undo(Some(String::from("missing")))),
+        Err(Error::JournalNotFound { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn resume_interrupted_run() -> Result<(), Error> {
+    use mmv::files::{journal::Journal, plan::Step};
+    let manager = FileManager::from_test_name(TestName::ResumeInterruptedRun);
+    manager.create_files(&vec!["a.txt", "b.txt"]);
+    let directory = manager.get_directory();
+    let state_dir = Some(directory.join("state"));
+    let steps = ["a", "b"].map(|name| Step {
+        source: directory.join(format!("{}.txt", name)),
+        target: directory.join(format!("{}.md", name)),
+    });
+    let mut journal = Journal::create(&directory.join("state"), &steps, Default::default())?;
+    std::fs::rename(&steps[0].source, &steps[0].target).unwrap();
+    journal.done(0)?;
+    let arguments = Arguments {
+        input_template: template(&manager, "*.md"),
+        output_template: template(&manager, "#1.txt"),
+        state_dir: state_dir.clone(),
+        ..Default::default()
+    };
+    assert!(matches!(
+        run(arguments),
+        Err(Error::UnfinishedJournal { .. })
+    ));
+    run(Arguments {
+        resume: Some(None),
+        state_dir,
+        ..Default::default()
+    })?;
+    assert!(directory.join("a.md").exists());
+    assert!(directory.join("b.md").exists());
+    Ok(())
+}
+
+#[test]
+fn copy_and_undo() -> Result<(), Error> {
+    let manager = FileManager::from_test_name(TestName::CopyAndUndo);
+    manager.create_files(&vec!["a.txt", "b.txt"]);
+    let directory = manager.get_directory();
+    let state_dir = Some(directory.join("state"));
+    run(Arguments {
+        input_template: template(&manager, "*.txt"),
+        output_template: template(&manager, "delivery/#1.md"),
+        copy: true,
+        mkdir: true,
+        state_dir: state_dir.clone(),
+        ..Default::default()
+    })?;
+    for name in ["a.txt", "b.txt", "delivery/a.md", "delivery/b.md"] {
+        assert!(directory.join(name).exists());
+    }
+    run(Arguments {
+        undo: Some(None),
+        state_dir,
+        ..Default::default()
+    })?;
+    assert!(directory.join("a.txt").exists());
+    assert!(!directory.join("delivery/a.md").exists());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_and_undo() -> Result<(), Error> {
+    use std::{fs, path::Path};
+    let manager = FileManager::from_test_name(TestName::SymlinkAndUndo);
+    let directory = manager.get_directory();
+    fs::create_dir(directory.join("links")).unwrap();
+    manager.create_files(&vec!["a.txt", "links/a.md"]);
+    let state_dir = Some(directory.join("state"));
+    let arguments = |force: bool| Arguments {
+        input_template: template(&manager, "*.txt"),
+        output_template: template(&manager, "links/#1.md"),
+        symlink: true,
+        relative: true,
+        force,
+        state_dir: state_dir.clone(),
+        ..Default::default()
+    };
+    assert!(matches!(
+        run(arguments(false)),
+        Err(Error::FilenameAlreadyExists { .. })
+    ));
+    run(arguments(true))?;
+    let link = directory.join("links/a.md");
+    assert_eq!(fs::read_link(&link).unwrap(), Path::new("../a.txt"));
+    assert_eq!(fs::read_to_string(&link).unwrap(), "Original name: a.txt");
+    run(Arguments {
+        undo: Some(None),
+        state_dir,
+        ..Default::default()
+    })?;
+    assert!(directory.join("a.txt").exists());
+    assert!(link.symlink_metadata().is_err());
+    Ok(())
+}
+
+#[test]
+fn relative_symlink_to_parent() -> Result<(), Error> {
+    use std::fs;
+    let manager = FileManager::from_test_name(TestName::RelativeSymlinkToParent);
+    let directory = manager.get_directory();
+    fs::create_dir_all(directory.join("proj/build")).unwrap();
+    fs::create_dir(directory.join("stage")).unwrap();
+    manager.create_files(&vec!["proj/build/lib.so"]);
+    run(Arguments {
+        input_template: template(&manager, "proj/build/*.so"),
+        output_template: template(&manager, "proj/../stage/#1.so"),
+        symlink: true,
+        relative: true,
+        ..Default::default()
+    })?;
+    let link = directory.join("stage/lib.so");
+    assert_eq!(
+        fs::read_link(&link).unwrap(),
+        std::path::Path::new("../proj/build/lib.so")
+    );
+    assert_eq!(
+        fs::read_to_string(&link).unwrap(),
+        "Original name: proj/build/lib.so"
+    );
+    Ok(())
+}
+
+#[test]
+fn on_conflict() -> Result<(), Error> {
+    use mmv::files::{conflict::OnConflict, r#move::Outcome};
+    use std::fs;
+    let manager = FileManager::from_test_name(TestName::OnConflict);
+    manager.create_files(&vec!["a.txt", "b.txt", "a.md"]);
+    let directory = manager.get_directory();
+    let arguments = |on_conflict: OnConflict| Arguments {
+        input_template: template(&manager, "*.txt"),
+        output_template: template(&manager, "#1.md"),
+        on_conflict: Some(on_conflict),
+        rename_suffix: Some(String::from(" (#)")),
+        ..Default::default()
+    };
+    assert_eq!(
+        run(arguments(OnConflict::Skip))?,
+        Outcome::Partial { skipped: 1 }
+    );
+    assert_eq!(
+        fs::read_to_string(directory.join("a.md")).unwrap(),
+        "Original name: a.md"
+    );
+    assert!(directory.join("a.txt").exists() && directory.join("b.md").exists());
+    run(arguments(OnConflict::Rename))?;
+    assert_eq!(
+        fs::read_to_string(directory.join("a (1).md")).unwrap(),
+        "Original name: a.txt"
+    );
+    assert!(!directory.join("a.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn backup_and_undo() -> Result<(), Error> {
+    use clap::Parser;
+    use std::fs;
+    let manager = FileManager::from_test_name(TestName::BackupAndUndo);
+    manager.create_files(&vec!["a.txt", "a.md"]);
+    let directory = manager.get_directory();
+    let state_dir = template(&manager, "state");
+    let arguments = |extra: &[&str]| {
+        let mut arguments = vec!["mmv", "--state-dir", &state_dir];
+        arguments.extend(extra);
+        Arguments::try_parse_from(arguments).unwrap()
+    };
+    let (input, output) = (template(&manager, "*.txt"), template(&manager, "#1.md"));
+    run(arguments(&[
+        "--force",
+        "--backup",
+        "--suffix=.bak",
+        &input,
+        &output,
+    ]))?;
+    assert_eq!(
+        fs::read_to_string(directory.join("a.md")).unwrap(),
+        "Original name: a.txt"
+    );
+    assert_eq!(
+        fs::read_to_string(directory.join("a.md.bak")).unwrap(),
+        "Original name: a.md"
+    );
+    run(arguments(&["--undo"]))?;
+    assert_eq!(
+        fs::read_to_string(directory.join("a.md")).unwrap(),
+        "Original name: a.md"
+    );
+    assert!(directory.join("a.txt").exists() && !directory.join("a.md.bak").exists());
+    Ok(())
+}
+
+#[test]
+fn interactive() {
+    use mmv::files::prompt::{Answer, Prompt};
+    use mmv::run_with;
+
+    /// Answers every question with the result of the function and remembers questions
+    struct Script<F: Fn(&str) -> Answer>(F, Vec<String>);
+
+    impl<F: Fn(&str) -> Answer> Prompt for Script<F> {
+        fn ask(&mut self, question: &str) -> std::io::Result<Answer> {
+            self.1.push(question.to_string());
+            Ok(self.0(question))
+        }
+    }
+
+    let manager = FileManager::from_test_name(TestName::Interactive);
+    manager.create_files(&vec!["a.txt", "b.txt", "c.txt"]);
+    let directory = manager.get_directory();
+    let arguments = || Arguments {
+        input_template: template(&manager, "*.txt"),
+        output_template: template(&manager, "#1.md"),
+        interactive: true,
+        ..Default::default()
+    };
+
+    let mut quit = Script(|_: &str| Answer::Quit, vec![]);
+    assert!(matches!(
+        run_with(arguments(), &mut quit),
+        Err(Error::Aborted)
+    ));
+    assert_eq!(quit.1.len(), 1);
+    assert!(directory.join("a.txt").exists() && directory.join("b.txt").exists());
+
+    let mut script = Script(
+        |question: &str| {
+            if question.contains("a.txt") {
+                Answer::No
+            } else {
+                Answer::Yes
+            }
+        },
+        vec![],
+    );
+    run_with(arguments(), &mut script).unwrap();
+    assert!(script.1[0].starts_with("rename "));
+    assert_eq!(script.1.len(), 3);
+    assert!(directory.join("a.txt").exists() && !directory.join("a.md").exists());
+    assert!(directory.join("b.md").exists() && directory.join("c.md").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn edit_targets() -> Result<(), Error> {
+    use std::{fs, os::unix::fs::PermissionsExt};
+    let manager = FileManager::from_test_name(TestName::EditTargets);
+    manager.create_files(&vec!["a.txt", "b.txt", "c.txt"]);
+    let directory = manager.get_directory();
+    let editor = directory.join("editor.sh");
+    fs::write(
+        &editor,
+        "#!/bin/sh\nsed -e 's/\\ta.md$/\\tnotes.md/' -e '/\\tc.txt\\t/d' \"$1\" > \"$1.new\"\nmv \"$1.new\" \"$1\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+    let arguments = |editor: &str| Arguments {
+        input_template: template(&manager, "*.txt"),
+        output_template: template(&manager, "#1.md"),
+        edit: true,
+        editor: Some(editor.to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        run(arguments("false")),
+        Err(Error::EditorFailed { .. })
+    ));
+    assert!(directory.join("a.txt").exists());
+    run(arguments(editor.to_str().unwrap()))?;
+    assert_eq!(
+        fs::read_to_string(directory.join("notes.md")).unwrap(),
+        "Original name: a.txt"
+    );
+    assert!(directory.join("b.md").exists() && directory.join("c.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn exit_codes() {
+    use std::process::Command;
+    let manager = FileManager::from_test_name(TestName::ExitCodes);
+    manager.create_files(&vec!["a.txt", "b.txt", "a.md"]);
+    let directory = manager.get_directory();
+    let mmv = |arguments: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_mmv"))
+            .arg("--state-dir")
+            .arg(directory.join("state"))
+            .args(arguments)
+            .output()
+            .unwrap()
+    };
+
+    let output = mmv(&[&template(&manager, "*.jpg"), &template(&manager, "#1.png")]);
+    assert_eq!(output.status.code(), Some(15));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not found"));
+
+    let output = mmv(&[&template(&manager, "*.txt"), &template(&manager, "#1.md")]);
+    assert_eq!(output.status.code(), Some(20));
+    let output = mmv(&[
+        "--on-conflict=skip",
+        &template(&manager, "*.txt"),
+        &template(&manager, "#1.md"),
+    ]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(directory.join("b.md").exists() && directory.join("a.txt").exists());
+    let output = mmv(&["--undo"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(mmv(&["--undo"]).status.code(), Some(32));
+}
+
 #[test]
 fn no_matched_files() {
     let manager = FileManager::from_test_name(TestName::NoMatchedFiles);
@@ -91,9 +680,8 @@ fn force_flag_test(test_name: TestName) -> Result<(), Error> {
 
 #[test]
 fn file_exists_no_force() {
-    force_flag_test(TestName::FileExistsNoForce).expect_err(&format!(
-        "Not able to replace existing file \"do_not_replace.me\""
-    ));
+    force_flag_test(TestName::FileExistsNoForce)
+        .expect_err("Not able to replace existing file \"do_not_replace.me\"");
 }
 
 #[test]
@@ -109,8 +697,8 @@ fn different_directories() {
     let filenames = vec!["foo.txt", "bar.txt"];
     submanager_1.create_files(&filenames);
     assert!(check_run(RunParams {
-        input_directory: &submanager_1.get_directory(),
-        output_directory: &submanager_2.get_directory(),
+        input_directory: submanager_1.get_directory(),
+        output_directory: submanager_2.get_directory(),
         input_pattern: "*",
         output_pattern: "#1",
         filenames_to_match: filenames.clone(),
@@ -119,3 +707,22 @@ fn different_directories() {
     })
     .is_ok());
 }
+
+#[cfg(unix)]
+#[test]
+fn non_unicode_filenames() -> Result<(), Error> {
+    use std::{ffi::OsString, fs, os::unix::ffi::OsStringExt};
+    let manager = FileManager::from_test_name(TestName::NonUnicode);
+    let directory = manager.get_directory();
+    let origin = OsString::from_vec(vec![b'r', 0xe9, b's', b'_', b'1', b'.', b't', b'x', b't']);
+    let changed = OsString::from_vec(vec![b'1', b'_', b'r', 0xe9, b's', b'.', b't', b'x', b't']);
+    fs::write(directory.join(&origin), "latin-1").unwrap();
+    run(Arguments {
+        input_template: directory.join("*_*.txt").to_str().unwrap().to_string(),
+        output_template: directory.join("#2_#1.txt").to_str().unwrap().to_string(),
+        ..Default::default()
+    })?;
+    assert!(!directory.join(&origin).exists());
+    assert_eq!(fs::read(directory.join(&changed)).unwrap(), b"latin-1");
+    Ok(())
+}
@@ -5,9 +5,7 @@ use std::{
 
 use super::utils::{create_directory, get_path, TestName};
 
-#[path = "../config.rs"]
-mod config;
-use config::should_clean_test_data;
+use super::config::should_clean_test_data;
 pub struct FileManager {
     working_directory: PathBuf,
 }
@@ -1,3 +1,6 @@
+#[path = "../config.rs"]
+mod config;
 pub mod file_manager;
 pub mod run;
+#[allow(clippy::module_inception)]
 pub mod utils;
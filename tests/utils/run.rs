@@ -44,6 +44,7 @@ pub fn check_run(params: RunParams) -> Result<(), Error> {
                 .unwrap(),
         ),
         force: params.force.unwrap_or(false),
+        ..Default::default()
     };
     let mut metadata: Vec<FileMeta> = vec![];
     for filename in params.filenames_to_match {
@@ -54,11 +55,10 @@ pub fn check_run(params: RunParams) -> Result<(), Error> {
         });
     }
     run(arguments)?;
-    for i in 0..params.modified_filenames.len() {
-        let filename = &params.modified_filenames[i];
+    for (filename, meta) in params.modified_filenames.iter().zip(metadata) {
         let path = params.output_directory.join(filename);
         assert_eq!(
-            metadata[i],
+            meta,
             FileMeta {
                 created: path.metadata().unwrap().created().unwrap(),
                 content: read(path).unwrap(),
@@ -88,3 +88,12 @@ pub fn do_run(
     })?;
     Ok(())
 }
+
+pub fn template(manager: &FileManager, path: &str) -> String {
+    manager
+        .get_directory()
+        .join(path)
+        .to_str()
+        .unwrap()
+        .to_string()
+}
@@ -1,9 +1,7 @@
 use std::fs::create_dir_all;
 use std::path::PathBuf;
 
-#[path = "../config.rs"]
-mod config;
-use config::get_working_directory;
+use super::config::get_working_directory;
 
 pub enum TestName {
     OkSimple,
@@ -12,6 +10,29 @@ pub enum TestName {
     FileExistsNoForce,
     FileExistsForceEnabled,
     DifferentDirectories,
+    NonUnicode,
+    OkAlternation,
+    OkRegex,
+    InvalidRegex,
+    Recursive,
+    DirectoryWildcards,
+    CreateDirectories,
+    IgnoreCase,
+    OkModifiers,
+    DryRun,
+    VerbosityFlags,
+    TargetCollision,
+    SwapNames,
+    Undo,
+    ResumeInterruptedRun,
+    CopyAndUndo,
+    SymlinkAndUndo,
+    RelativeSymlinkToParent,
+    OnConflict,
+    BackupAndUndo,
+    Interactive,
+    EditTargets,
+    ExitCodes,
 }
 
 pub fn get_path(test_name: TestName) -> PathBuf {
@@ -23,9 +44,31 @@ pub fn get_path(test_name: TestName) -> PathBuf {
         TestName::FileExistsNoForce => "file_exists_no_force/",
         TestName::FileExistsForceEnabled => "file_exists_force_enabled/",
         TestName::DifferentDirectories => "different_directories/",
+        TestName::NonUnicode => "non_unicode/",
+        TestName::OkAlternation => "ok_alternation/",
+        TestName::OkRegex => "ok_regex/",
+        TestName::InvalidRegex => "invalid_regex/",
+        TestName::Recursive => "recursive/",
+        TestName::DirectoryWildcards => "directory_wildcards/",
+        TestName::CreateDirectories => "create_directories/",
+        TestName::IgnoreCase => "ignore_case/",
+        TestName::OkModifiers => "ok_modifiers/",
+        TestName::DryRun => "dry_run/",
+        TestName::VerbosityFlags => "verbosity_flags/",
+        TestName::TargetCollision => "target_collision/",
+        TestName::SwapNames => "swap_names/",
+        TestName::Undo => "undo/",
+        TestName::ResumeInterruptedRun => "resume_interrupted_run/",
+        TestName::CopyAndUndo => "copy_and_undo/",
+        TestName::SymlinkAndUndo => "symlink_and_undo/",
+        TestName::RelativeSymlinkToParent => "relative_symlink_to_parent/",
+        TestName::OnConflict => "on_conflict/",
+        TestName::BackupAndUndo => "backup_and_undo/",
+        TestName::Interactive => "interactive/",
+        TestName::EditTargets => "edit_targets/",
+        TestName::ExitCodes => "exit_codes/",
     };
-    let final_directory = working_directory.join(test_subdirectory);
-    final_directory
+    working_directory.join(test_subdirectory)
 }
 
 pub fn create_directory(path: &PathBuf) {